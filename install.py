@@ -11,16 +11,19 @@ def main():
     src_dir =  path.dirname(path.abspath(__file__))
     binary_src = f'{src_dir}/target/release/umberwm'
     binary_dest = '/usr/bin/umberwm'
+    msg_binary_src = f'{src_dir}/target/release/umberwm-msg'
+    msg_binary_dest = '/usr/bin/umberwm-msg'
     start_script_src = f'{src_dir}/umberwm-start'
     start_script_dest = '/usr/bin/umberwm-start'
     desktop_src = f'{src_dir}/umberwm.desktop'
     desktop_dest = '/usr/share/xsessions/umberwm.desktop'
 
-    # Bail if binary does not exist
-    if not path.exists(binary_src):
+    # Bail if binaries do not exist
+    if not path.exists(binary_src) or not path.exists(msg_binary_src):
         exit("Error: binary not found. Run 'cargo build --release' and try again.")
-    # Symlink binary to /usr/bin
+    # Symlink binaries to /usr/bin
     symlink(binary_src, binary_dest)
+    symlink(msg_binary_src, msg_binary_dest)
     # Symlink start script to /usr/bin
     symlink(start_script_src, start_script_dest)
     # Copy desktop file to /usr/share/xsessions (it cannot be symlinked)
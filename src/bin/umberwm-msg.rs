@@ -0,0 +1,32 @@
+use ::umberwm::ipc::socket_path;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::process::exit;
+
+fn main() {
+    let command = env::args().skip(1).collect::<Vec<String>>().join(" ");
+    if command.is_empty() {
+        eprintln!("usage: umberwm-msg <command> [argument]");
+        exit(2);
+    }
+    let path = socket_path();
+    let mut stream = UnixStream::connect(&path).unwrap_or_else(|e| {
+        eprintln!("failed to connect to {}: {}", path.display(), e);
+        exit(1);
+    });
+    writeln!(stream, "{}", command).unwrap();
+    let mut failed = false;
+    for line in BufReader::new(stream).lines() {
+        match line {
+            Ok(line) => {
+                failed |= line.starts_with("error");
+                println!("{}", line);
+            }
+            Err(_) => break,
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
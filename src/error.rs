@@ -17,11 +17,21 @@ pub enum Error {
     NoMouseMoveStart,
     #[error("No butten press geometry")]
     NoButtonPressGeometry,
+    #[error("Another window manager is already running")]
+    AnotherWmRunning,
+    #[error("Invalid IPC command: {0}")]
+    InvalidIpcCommand(String),
+    #[error("IPC subscriber stopped reading events")]
+    IpcSubscriberTooSlow,
+    #[error("Invalid quota for workspace {0}: {1} (must be between 0 and 1)")]
+    InvalidQuota(String, f32),
     #[error("Failed to deserialize from JSON: {0}")]
     FailedToDeserializeFromJson(String),
     #[error(transparent)]
     Error(#[from] ron::error::Error),
     #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
     FromUtf8(#[from] std::string::FromUtf8Error),
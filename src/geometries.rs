@@ -1,4 +1,5 @@
-use crate::model::{Geometry, GeometryInfo};
+use crate::model::{Direction, DisplayBorder, Geometry, GeometryInfo, SplitNode, Strut, Window};
+use std::cmp::{max, min};
 
 pub fn with_quota(quota: f32, size: u32) -> u32 {
     (size as f32 * quota) as u32
@@ -45,3 +46,500 @@ pub fn geometries_bsp(
         res
     }
 }
+
+/// Splits an area in two as the split tree does at this depth: the height is split if
+/// `depth % 2 == vertical`, as in `geometries_bsp`, the width otherwise.
+fn split_in_two(ratio: f32, depth: usize, vertical: usize, area: Geometry) -> (Geometry, Geometry) {
+    let Geometry(left, top, width, height) = area;
+    if depth % 2 == vertical {
+        let ratio_height = with_quota(ratio, height);
+        (
+            Geometry(left, top, width, ratio_height),
+            Geometry(left, top + ratio_height, width, height - ratio_height),
+        )
+    } else {
+        let ratio_width = with_quota(ratio, width);
+        (
+            Geometry(left, top, ratio_width, height),
+            Geometry(left + ratio_width, top, width - ratio_width, height),
+        )
+    }
+}
+
+/// Geometries of the windows of a split tree, in order, splitting the same way as
+/// `geometries_bsp` at each depth but with the ratio of each split.
+pub fn geometries_tree(
+    node: &SplitNode,
+    depth: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    vertical: usize,
+) -> Vec<Geometry> {
+    match node {
+        SplitNode::Window(_) => vec![Geometry(left, top, width, height)],
+        SplitNode::Split {
+            ratio,
+            first,
+            second,
+        } => {
+            let (Geometry(x, y, w, h), second_area) =
+                split_in_two(*ratio, depth, vertical, Geometry(left, top, width, height));
+            let mut res = geometries_tree(first, depth + 1, x, y, w, h, vertical);
+            let Geometry(x, y, w, h) = second_area;
+            res.append(&mut geometries_tree(
+                second,
+                depth + 1,
+                x,
+                y,
+                w,
+                h,
+                vertical,
+            ));
+            res
+        }
+    }
+}
+
+/// Returns the area of the split directly containing the window in a split tree laid out as in
+/// `geometries_tree`, and whether this split divides the height.
+pub fn split_area(
+    node: &SplitNode,
+    window: Window,
+    depth: usize,
+    area: Geometry,
+    vertical: usize,
+) -> Option<(Geometry, bool)> {
+    match node {
+        SplitNode::Window(_) => None,
+        SplitNode::Split {
+            ratio,
+            first,
+            second,
+        } => {
+            if first.is_window(window) || second.is_window(window) {
+                return Some((area, depth % 2 == vertical));
+            }
+            let (first_area, second_area) = split_in_two(*ratio, depth, vertical, area);
+            split_area(first, window, depth + 1, first_area, vertical)
+                .or_else(|| split_area(second, window, depth + 1, second_area, vertical))
+        }
+    }
+}
+
+/// Spiral variant of `geometries_bsp`: each window takes `info.quota` of the remaining space on
+/// a side which rotates clockwise (left, top, right, bottom), so that the remaining space winds
+/// towards the middle of the display instead of a corner.
+pub fn geometries_spiral(
+    i: usize,
+    window_count: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    info: &GeometryInfo,
+) -> Vec<Geometry> {
+    if window_count == 0 {
+        return vec![];
+    } else if window_count == 1 {
+        return vec![Geometry(left, top, width, height)];
+    }
+    let quota_width = with_quota(info.quota, width);
+    let quota_height = with_quota(info.quota, height);
+    let (window, remaining) = match i % 4 {
+        0 => (
+            Geometry(left, top, quota_width, height),
+            Geometry(left + quota_width, top, width - quota_width, height),
+        ),
+        1 => (
+            Geometry(left, top, width, quota_height),
+            Geometry(left, top + quota_height, width, height - quota_height),
+        ),
+        2 => (
+            Geometry(left + width - quota_width, top, quota_width, height),
+            Geometry(left, top, width - quota_width, height),
+        ),
+        _ => (
+            Geometry(left, top + height - quota_height, width, quota_height),
+            Geometry(left, top, width, height - quota_height),
+        ),
+    };
+    let Geometry(left, top, width, height) = remaining;
+    let mut res = vec![window];
+    res.append(&mut geometries_spiral(
+        i + 1,
+        window_count - 1,
+        left,
+        top,
+        width,
+        height,
+        info,
+    ));
+    res
+}
+
+/// Splits `size` in `count` equal parts starting at `start`, the last part taking the remainder.
+fn split_evenly(start: u32, size: u32, count: usize) -> Vec<(u32, u32)> {
+    let count = count as u32;
+    let part = size / count;
+    (0..count)
+        .map(|i| {
+            let offset = i * part;
+            if i + 1 == count {
+                (start + offset, size - offset)
+            } else {
+                (start + offset, part)
+            }
+        })
+        .collect()
+}
+
+/// Stacks `window_count` windows evenly, one above the other if `vertical`, else side by side.
+fn geometries_stack(
+    window_count: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    vertical: bool,
+) -> Vec<Geometry> {
+    if window_count == 0 {
+        vec![]
+    } else if vertical {
+        split_evenly(top, height, window_count)
+            .into_iter()
+            .map(|(y, h)| Geometry(left, y, width, h))
+            .collect()
+    } else {
+        split_evenly(left, width, window_count)
+            .into_iter()
+            .map(|(x, w)| Geometry(x, top, w, height))
+            .collect()
+    }
+}
+
+/// Master/stack layout: the first `info.master_count` windows share `info.quota` of the
+/// display, on the left if `info.vertical` is 1 (tall) or at the top otherwise (wide), and the
+/// others share the rest.
+pub fn geometries_master(
+    window_count: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    info: &GeometryInfo,
+) -> Vec<Geometry> {
+    let masters = min(info.master_count, window_count);
+    let stacked = window_count - masters;
+    let tall = info.vertical == 1;
+    let size = if tall { width } else { height };
+    let master_size = if stacked == 0 {
+        size
+    } else if masters == 0 {
+        0
+    } else {
+        with_quota(info.quota, size)
+    };
+    let mut res;
+    if tall {
+        res = geometries_stack(masters, left, top, master_size, height, true);
+        res.append(&mut geometries_stack(
+            stacked,
+            left + master_size,
+            top,
+            width - master_size,
+            height,
+            true,
+        ));
+    } else {
+        res = geometries_stack(masters, left, top, width, master_size, false);
+        res.append(&mut geometries_stack(
+            stacked,
+            left,
+            top + master_size,
+            width,
+            height - master_size,
+            false,
+        ));
+    }
+    res
+}
+
+/// Lays out windows in rows of equal height, as square a grid as possible, the last row
+/// sharing its width between the remaining windows.
+pub fn geometries_grid(
+    window_count: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+) -> Vec<Geometry> {
+    if window_count == 0 {
+        return vec![];
+    }
+    let columns = (window_count as f32).sqrt().ceil() as usize;
+    let rows = window_count.div_ceil(columns);
+    let mut res = vec![];
+    for (row, (y, h)) in split_evenly(top, height, rows).into_iter().enumerate() {
+        let count = min(columns, window_count - row * columns);
+        res.append(&mut geometries_stack(count, left, y, width, h, false));
+    }
+    res
+}
+
+/// Lays out windows side by side in columns of equal width.
+pub fn geometries_columns(
+    window_count: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+) -> Vec<Geometry> {
+    geometries_stack(window_count, left, top, width, height, false)
+}
+
+/// Centered master layout: the first `info.master_count` windows share a column of
+/// `info.quota` of the width in the middle of the display, and the others alternate between a
+/// column on the left and one on the right.
+pub fn geometries_centered_master(
+    window_count: usize,
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    info: &GeometryInfo,
+) -> Vec<Geometry> {
+    let masters = min(info.master_count, window_count);
+    let stacked = window_count - masters;
+    if masters == 0 || stacked == 0 {
+        return geometries_stack(window_count, left, top, width, height, true);
+    }
+    let master_width = with_quota(info.quota, width);
+    if stacked == 1 {
+        let mut res = geometries_stack(masters, left, top, master_width, height, true);
+        res.push(Geometry(
+            left + master_width,
+            top,
+            width - master_width,
+            height,
+        ));
+        return res;
+    }
+    let left_width = (width - master_width) / 2;
+    let right_width = width - master_width - left_width;
+    let mut res = geometries_stack(masters, left + left_width, top, master_width, height, true);
+    let mut left_column =
+        geometries_stack(stacked / 2, left, top, left_width, height, true).into_iter();
+    let mut right_column = geometries_stack(
+        stacked - stacked / 2,
+        left + left_width + master_width,
+        top,
+        right_width,
+        height,
+        true,
+    )
+    .into_iter();
+    for i in 0..stacked {
+        let column = if i % 2 == 0 {
+            &mut right_column
+        } else {
+            &mut left_column
+        };
+        res.extend(column.next());
+    }
+    res
+}
+
+/// Returns the index of the geometry nearest to `from` in the direction, among those lying
+/// entirely on that side of it: the closest edge wins, then the closest center.
+pub fn nearest_in_direction(
+    from: &Geometry,
+    geometries: &[Geometry],
+    direction: &Direction,
+) -> Option<usize> {
+    let edges = |Geometry(x, y, width, height): &Geometry| {
+        let (x, y, width, height) = (*x as i64, *y as i64, *width as i64, *height as i64);
+        (x, y, x + width, y + height, x + width / 2, y + height / 2)
+    };
+    let (left, top, right, bottom, center_x, center_y) = edges(from);
+    geometries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, geometry)| {
+            let (g_left, g_top, g_right, g_bottom, g_center_x, g_center_y) = edges(geometry);
+            let (distance, offset) = match direction {
+                Direction::Left => (left - g_right, g_center_y - center_y),
+                Direction::Right => (g_left - right, g_center_y - center_y),
+                Direction::Up => (top - g_bottom, g_center_x - center_x),
+                Direction::Down => (g_top - bottom, g_center_x - center_x),
+            };
+            if distance >= 0 {
+                Some((i, (distance, offset.abs())))
+            } else {
+                None
+            }
+        })
+        .min_by_key(|(_, key)| *key)
+        .map(|(i, _)| i)
+}
+
+fn overlaps(start: u32, end: u32, display_start: u32, display_size: u32) -> bool {
+    start < display_start + display_size && end >= display_start
+}
+
+/// Returns the space a dock strut reserves on a display, given the size of the whole screen.
+pub fn strut_border(
+    strut: &Strut,
+    display: &Geometry,
+    screen_width: u32,
+    screen_height: u32,
+) -> DisplayBorder {
+    let Geometry(x, y, width, height) = *display;
+    let mut border = DisplayBorder {
+        left: 0,
+        right: 0,
+        top: 0,
+        bottom: 0,
+        gap: 0,
+    };
+    if overlaps(strut.left_start_y, strut.left_end_y, y, height) {
+        border.left = strut.left.saturating_sub(x);
+    }
+    if overlaps(strut.right_start_y, strut.right_end_y, y, height) {
+        border.right = (x + width).saturating_sub(screen_width.saturating_sub(strut.right));
+    }
+    if overlaps(strut.top_start_x, strut.top_end_x, x, width) {
+        border.top = strut.top.saturating_sub(y);
+    }
+    if overlaps(strut.bottom_start_x, strut.bottom_end_x, x, width) {
+        border.bottom = (y + height).saturating_sub(screen_height.saturating_sub(strut.bottom));
+    }
+    border.left = border.left.min(width);
+    border.right = border.right.min(width);
+    border.top = border.top.min(height);
+    border.bottom = border.bottom.min(height);
+    border
+}
+
+/// Combines a configured display border with the space reserved by docks.
+pub fn merge_borders(configured: &DisplayBorder, reserved: &DisplayBorder) -> DisplayBorder {
+    DisplayBorder {
+        left: max(configured.left, reserved.left),
+        right: max(configured.right, reserved.right),
+        top: max(configured.top, reserved.top),
+        bottom: max(configured.bottom, reserved.bottom),
+        gap: configured.gap,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(quota: f32) -> GeometryInfo {
+        GeometryInfo {
+            vertical: 1,
+            quota,
+            master_count: 1,
+        }
+    }
+
+    #[test]
+    fn geometries_tree_uses_the_ratio_of_each_split() {
+        let tree = SplitNode::Split {
+            ratio: 0.25,
+            first: Box::new(SplitNode::Window(1)),
+            second: Box::new(SplitNode::Split {
+                ratio: 0.5,
+                first: Box::new(SplitNode::Window(2)),
+                second: Box::new(SplitNode::Window(3)),
+            }),
+        };
+        assert_eq!(
+            geometries_tree(&tree, 0, 0, 0, 100, 80, 1),
+            vec![
+                Geometry(0, 0, 25, 80),
+                Geometry(25, 0, 75, 40),
+                Geometry(25, 40, 75, 40),
+            ]
+        );
+        assert_eq!(
+            geometries_tree(&tree, 0, 0, 0, 100, 80, 0),
+            vec![
+                Geometry(0, 0, 100, 20),
+                Geometry(0, 20, 50, 60),
+                Geometry(50, 20, 50, 60),
+            ]
+        );
+    }
+
+    #[test]
+    fn geometries_spiral_winds_clockwise() {
+        assert_eq!(
+            geometries_spiral(0, 4, 0, 0, 100, 100, &info(0.5)),
+            vec![
+                Geometry(0, 0, 50, 100),
+                Geometry(50, 0, 50, 50),
+                Geometry(75, 50, 25, 50),
+                Geometry(50, 50, 25, 50),
+            ]
+        );
+        assert_eq!(
+            geometries_spiral(0, 1, 10, 20, 100, 100, &info(0.5)),
+            vec![Geometry(10, 20, 100, 100)]
+        );
+        assert!(geometries_spiral(0, 0, 0, 0, 100, 100, &info(0.5)).is_empty());
+    }
+
+    #[test]
+    fn strut_border_only_reserves_space_on_the_displays_the_dock_covers() {
+        // a 1920x1080 display next to a 1280x1024 one
+        let left_display = Geometry(0, 0, 1920, 1080);
+        let right_display = Geometry(1920, 0, 1280, 1024);
+        let top_panel = Strut {
+            top: 30,
+            top_end_x: 1919,
+            ..Default::default()
+        };
+        assert_eq!(strut_border(&top_panel, &left_display, 3200, 1080).top, 30);
+        assert_eq!(strut_border(&top_panel, &right_display, 3200, 1080).top, 0);
+        let right_dock = Strut {
+            right: 100,
+            right_end_y: 1023,
+            ..Default::default()
+        };
+        assert_eq!(
+            strut_border(&right_dock, &right_display, 3200, 1080).right,
+            100
+        );
+        assert_eq!(
+            strut_border(&right_dock, &left_display, 3200, 1080).right,
+            0
+        );
+        // the bottom strut is relative to the screen, which is taller than the right display
+        let bottom_panel = Strut {
+            bottom: 100,
+            bottom_start_x: 1920,
+            bottom_end_x: 3199,
+            ..Default::default()
+        };
+        assert_eq!(
+            strut_border(&bottom_panel, &right_display, 3200, 1080).bottom,
+            44
+        );
+    }
+
+    #[test]
+    fn strut_border_is_capped_to_the_display() {
+        let strut = Strut {
+            left: 5000,
+            left_end_y: 1079,
+            ..Default::default()
+        };
+        assert_eq!(
+            strut_border(&strut, &Geometry(0, 0, 1920, 1080), 1920, 1080).left,
+            1920
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::error::{Error, Result};
+use crate::model::{Actions, WorkspaceName};
+use serde::Serialize;
+use std::env;
+use std::fs::remove_file;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::RawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use xcb::Window;
+
+/// Commands accepted on the IPC socket, one per line:
+///
+/// - `action <action>`: runs an action, written as in the `wm_actions` configuration (e.g.
+///   `action ChangeLayout`)
+/// - `workspace <name>`: switches to a workspace
+/// - `move_to_workspace <name>`: moves the focused window to a workspace and switches to it
+/// - `send_to_workspace <name>`: moves the focused window to a workspace, staying on the current
+///   one
+/// - `kill_window <id>`: kills the client owning a window, e.g. from an `OnUnresponsive` callback
+/// - `get_workspaces`, `get_tree`, `get_displays`, `get_focused`: replies with a snapshot of the
+///   state, as RON or, when followed by `json`, as JSON
+/// - `subscribe`: keeps the connection open and sends one line per event, as RON or, when
+///   followed by `json`, as JSON
+#[derive(Debug)]
+pub enum IpcCommand {
+    Action(Actions),
+    Workspace(WorkspaceName),
+    MoveToWorkspace(WorkspaceName),
+    SendToWorkspace(WorkspaceName),
+    KillWindow(Window),
+    Query(IpcQuery, IpcFormat),
+    Subscribe(IpcFormat),
+}
+
+#[derive(Debug)]
+pub enum IpcQuery {
+    Workspaces,
+    Tree,
+    Displays,
+    Focused,
+}
+
+#[derive(Debug)]
+pub enum IpcFormat {
+    Ron,
+    Json,
+}
+
+impl FromStr for IpcFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "" | "ron" => Ok(IpcFormat::Ron),
+            "json" => Ok(IpcFormat::Json),
+            _ => Err(Error::InvalidIpcCommand(format.to_string())),
+        }
+    }
+}
+
+impl IpcFormat {
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String> {
+        Ok(match self {
+            IpcFormat::Ron => ron::ser::to_string(value)?,
+            IpcFormat::Json => serde_json::to_string(value)?,
+        })
+    }
+}
+
+impl FromStr for IpcCommand {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (command, argument) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+        match (command, argument) {
+            ("action", argument) if !argument.is_empty() => Ok(IpcCommand::Action(
+                ron::de::from_str(argument)
+                    .map_err(|_| Error::InvalidIpcCommand(line.to_string()))?,
+            )),
+            ("workspace", name) if !name.is_empty() => Ok(IpcCommand::Workspace(name.to_string())),
+            ("move_to_workspace", name) if !name.is_empty() => {
+                Ok(IpcCommand::MoveToWorkspace(name.to_string()))
+            }
+            ("send_to_workspace", name) if !name.is_empty() => {
+                Ok(IpcCommand::SendToWorkspace(name.to_string()))
+            }
+            ("kill_window", id) => Ok(IpcCommand::KillWindow(
+                id.parse()
+                    .map_err(|_| Error::InvalidIpcCommand(line.to_string()))?,
+            )),
+            (query, format) if query.starts_with("get_") => {
+                let query = match query {
+                    "get_workspaces" => IpcQuery::Workspaces,
+                    "get_tree" => IpcQuery::Tree,
+                    "get_displays" => IpcQuery::Displays,
+                    "get_focused" => IpcQuery::Focused,
+                    _ => return Err(Error::InvalidIpcCommand(line.to_string())),
+                };
+                Ok(IpcCommand::Query(query, format.parse()?))
+            }
+            ("subscribe", format) => Ok(IpcCommand::Subscribe(format.parse()?)),
+            _ => Err(Error::InvalidIpcCommand(line.to_string())),
+        }
+    }
+}
+
+/// Returns the path of the IPC socket, which can be overridden with `UMBERWM_SOCKET`.
+///
+/// The socket lives in `$XDG_RUNTIME_DIR` and is named after `$DISPLAY`, so that nested
+/// umberwm instances (e.g. in Xephyr) do not share it.
+pub fn socket_path() -> PathBuf {
+    if let Ok(path) = env::var("UMBERWM_SOCKET") {
+        return PathBuf::from(path);
+    }
+    let dir = env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir());
+    let display = env::var("DISPLAY").unwrap_or_default();
+    dir.join(format!("umberwm{}.sock", display))
+}
+
+/// Binds the IPC socket, replacing any stale one left by a previous instance.
+pub fn bind() -> Result<UnixListener> {
+    let path = socket_path();
+    if path.exists() {
+        remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Reads what is available on a non-blocking client connection into `buffer`, and returns the
+/// first complete line, if any. The rest of the line is read on the next call.
+///
+/// A client which closes the connection without a final newline gets what it sent treated as
+/// a line.
+pub fn read_line(mut stream: &UnixStream, buffer: &mut Vec<u8>) -> Result<Option<String>> {
+    let mut chunk = [0; 1024];
+    loop {
+        if let Some(i) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=i).collect();
+            return Ok(Some(String::from_utf8(line)?));
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) if buffer.is_empty() => {
+                return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into())
+            }
+            Ok(0) => return Ok(Some(String::from_utf8(buffer.split_off(0))?)),
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Output a subscriber may lag behind by before being dropped.
+const MAX_PENDING_OUTPUT: usize = 1024 * 1024;
+
+/// A connection subscribed to events, along with the output it was not ready to read yet.
+pub struct IpcSubscriber {
+    pub stream: UnixStream,
+    pub format: IpcFormat,
+    pending: Vec<u8>,
+}
+
+impl IpcSubscriber {
+    pub fn new(stream: UnixStream, format: IpcFormat) -> Self {
+        IpcSubscriber {
+            stream,
+            format,
+            pending: vec![],
+        }
+    }
+
+    /// Queues a line and sends as much of the pending output as the subscriber can read now.
+    /// Fails if the subscriber went away or is too far behind.
+    pub fn send_line(&mut self, line: &str) -> Result<()> {
+        if self.pending.len() + line.len() + 1 > MAX_PENDING_OUTPUT {
+            return Err(Error::IpcSubscriberTooSlow);
+        }
+        self.pending.extend_from_slice(line.as_bytes());
+        self.pending.push(b'\n');
+        self.flush()
+    }
+
+    /// Sends as much of the pending output as the subscriber can read now.
+    pub fn flush(&mut self) -> Result<()> {
+        while !self.pending.is_empty() {
+            match (&self.stream).write(&self.pending) {
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::WriteZero).into()),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_pending_output(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+/// Blocks until one of the `readable` file descriptors is readable, one of the `writable` ones
+/// is writable, or the timeout expires.
+pub fn wait_for_io(readable: &[RawFd], writable: &[RawFd], timeout: Option<Duration>) {
+    let mut poll_fds: Vec<libc::pollfd> = readable
+        .iter()
+        .map(|fd| (fd, libc::POLLIN))
+        .chain(writable.iter().map(|fd| (fd, libc::POLLOUT)))
+        .map(|(fd, events)| libc::pollfd {
+            fd: *fd,
+            events,
+            revents: 0,
+        })
+        .collect();
+    unsafe {
+        libc::poll(
+            poll_fds.as_mut_ptr(),
+            poll_fds.len() as libc::nfds_t,
+            timeout.map_or(-1, |timeout| timeout.as_millis() as libc::c_int),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(
+            "workspace 3\n".parse::<IpcCommand>(),
+            Ok(IpcCommand::Workspace(name)) if name == "3"
+        ));
+        assert!(matches!(
+            "send_to_workspace  3 ".parse::<IpcCommand>(),
+            Ok(IpcCommand::SendToWorkspace(name)) if name == "3"
+        ));
+        assert!(matches!(
+            "action ChangeLayout".parse::<IpcCommand>(),
+            Ok(IpcCommand::Action(Actions::ChangeLayout))
+        ));
+        assert!(matches!(
+            "kill_window 42".parse::<IpcCommand>(),
+            Ok(IpcCommand::KillWindow(42))
+        ));
+        assert!(matches!(
+            "get_tree json".parse::<IpcCommand>(),
+            Ok(IpcCommand::Query(IpcQuery::Tree, IpcFormat::Json))
+        ));
+        assert!(matches!(
+            "get_focused".parse::<IpcCommand>(),
+            Ok(IpcCommand::Query(IpcQuery::Focused, IpcFormat::Ron))
+        ));
+        assert!(matches!(
+            "subscribe json".parse::<IpcCommand>(),
+            Ok(IpcCommand::Subscribe(IpcFormat::Json))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        for line in [
+            "",
+            "workspace",
+            "action",
+            "action NotAnAction",
+            "kill_window abc",
+            "get_windows",
+            "get_tree xml",
+            "subscribe xml",
+            "reboot",
+        ] {
+            assert!(
+                matches!(line.parse::<IpcCommand>(), Err(Error::InvalidIpcCommand(_))),
+                "{:?} should be rejected",
+                line
+            );
+        }
+    }
+}
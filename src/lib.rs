@@ -5,9 +5,11 @@ pub mod model;
 use crate::model::*;
 use xmodmap_pke_umberwm::xmodmap_pke;
 mod geometries;
+pub mod ipc;
 mod keycode;
 mod serializable_conf;
 mod serializable_state;
+mod split_tree;
 use serializable_state::load_serializable_state;
 mod umberwm_impl;
 use std::collections::HashMap;
@@ -18,6 +20,7 @@ pub fn umberwm_from_conf() -> Result<UmberWm> {
     Ok(umberwm(Conf {
         serializable: res,
         custom_actions: HashMap::new(),
+        custom_layouts: HashMap::new(),
         events_callbacks: EventsCallbacks {
             on_change_workspace: None,
         },
@@ -33,14 +36,29 @@ pub fn umberwm(conf: Conf) -> UmberWm {
         current_workspace: serializable_state.current_workspace,
         float_windows: serializable_state.float_windows,
         overlay_windows: serializable_state.overlay_windows,
+        fullscreen_windows: serializable_state.fullscreen_windows,
+        float_geometries: serializable_state.float_geometries,
+        docks: HashMap::new(),
+        transients: serializable_state.transients,
+        urgent_windows: Vec::new(),
+        pending_pings: HashMap::new(),
+        last_event_time: xcb::CURRENT_TIME,
         workspaces: serializable_state.workspaces,
         conn,
         button_press_geometry: None,
         mouse_move_start: None,
+        tiled_resize_start: None,
+        drop_target: None,
         xmodmap_pke: xmodmap_pke_res,
         displays_geometries: Vec::new(),
+        screen_size: (0, 0),
         randr_base: 0,
         previous_display: 0,
+        visible_workspaces: HashMap::new(),
+        ipc_listener: None,
+        ipc_clients: Vec::new(),
+        ipc_subscribers: Vec::new(),
+        check_window: 0,
     };
     wm.init();
     wm
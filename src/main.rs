@@ -1,6 +1,6 @@
 use ::umberwm::model::{
-    Actions, Conf, DisplayBorder, Events, EventsCallbacks, Keybind, SerializableConf, WindowBorder,
-    MOD_MASK_1, MOD_MASK_4, MOD_MASK_CONTROL, MOD_MASK_SHIFT,
+    Actions, Conf, DisplayBorder, Events, EventsCallbacks, Keybind, Layout, SerializableConf,
+    WindowBorder, MOD_MASK_1, MOD_MASK_4, MOD_MASK_CONTROL, MOD_MASK_SHIFT,
 };
 use ::umberwm::umberwm;
 use std::collections::HashMap;
@@ -16,6 +16,7 @@ fn main() {
             },
             // User defined actions
             custom_actions: HashMap::new(),
+            custom_layouts: HashMap::new(),
         };
         umberwm(conf).run();
     } else {
@@ -30,7 +31,8 @@ fn main() {
         let serializable = SerializableConf {
             // The mod key that is used to switch between workspaces
             meta,
-            // Borders defining space the WM wont tile windows to (useful when using task bars)
+            // Borders defining space the WM wont tile windows to (task bars reserving space with
+            // `_NET_WM_STRUT` are taken into account automatically)
             display_borders: vec![
                 DisplayBorder {
                     left: 0,
@@ -52,6 +54,8 @@ fn main() {
                 width: 1,
                 focus_color: 0x906cff,
                 normal_color: 0x000000,
+                urgent_color: 0xff0000,
+                drop_target_color: 0x00ff00,
             },
             // Key names of the workspaces (must be a name in `xmodmap -pke`)
             // Each Vec defines the workspaces for a single display. You should have as many Vecs as
@@ -68,7 +72,20 @@ fn main() {
                 (Keybind::new(meta, "space"), Actions::SwitchWindow),
                 (Keybind::new(meta, "w"), Actions::CloseWindow),
                 (Keybind::new(meta, "f"), Actions::ChangeLayout),
+                (
+                    Keybind::new(meta | MOD_MASK_CONTROL, "f"),
+                    Actions::PreviousLayout,
+                ),
                 (Keybind::new(meta, "g"), Actions::ToggleGap),
+                (Keybind::new(meta, "u"), Actions::FocusUrgent),
+                (
+                    Keybind::new(meta | MOD_MASK_SHIFT, "f"),
+                    Actions::ToggleFullscreen,
+                ),
+                (
+                    Keybind::new(meta | MOD_MASK_SHIFT, "space"),
+                    Actions::ToggleFloat,
+                ),
                 (
                     Keybind::new(meta | MOD_MASK_CONTROL, "h"),
                     Actions::SwapPreviousWindow,
@@ -77,6 +94,35 @@ fn main() {
                     Keybind::new(meta | MOD_MASK_CONTROL, "l"),
                     Actions::SwapNextWindow,
                 ),
+                (
+                    Keybind::new(meta | MOD_MASK_CONTROL, "i"),
+                    Actions::IncreaseMasterCount,
+                ),
+                (
+                    Keybind::new(meta | MOD_MASK_CONTROL, "d"),
+                    Actions::DecreaseMasterCount,
+                ),
+                (
+                    Keybind::new(meta | MOD_MASK_CONTROL, "Return"),
+                    Actions::PromoteToMaster,
+                ),
+                (Keybind::new(meta, "Left"), Actions::FocusLeft),
+                (Keybind::new(meta, "Right"), Actions::FocusRight),
+                (Keybind::new(meta, "Up"), Actions::FocusUp),
+                (Keybind::new(meta, "Down"), Actions::FocusDown),
+                (
+                    Keybind::new(meta | MOD_MASK_SHIFT, "Left"),
+                    Actions::SwapLeft,
+                ),
+                (
+                    Keybind::new(meta | MOD_MASK_SHIFT, "Right"),
+                    Actions::SwapRight,
+                ),
+                (Keybind::new(meta | MOD_MASK_SHIFT, "Up"), Actions::SwapUp),
+                (
+                    Keybind::new(meta | MOD_MASK_SHIFT, "Down"),
+                    Actions::SwapDown,
+                ),
                 (Keybind::new(meta | MOD_MASK_CONTROL, "q"), Actions::Quit),
                 (
                     // Restart UmberWM (if configured to do so - see README.md for details)
@@ -85,7 +131,30 @@ fn main() {
                 ),
             ]
             .into_iter()
+            // Send the focused window to a workspace without following it
+            .chain((1..=9).map(|i| {
+                (
+                    Keybind::new(meta | MOD_MASK_CONTROL, i.to_string()),
+                    Actions::SendToWorkspace(i.to_string()),
+                )
+            }))
             .collect(),
+            // Layouts to cycle through with `ChangeLayout` and `PreviousLayout`
+            layouts: vec![
+                Layout::Bspv,
+                Layout::Monocle,
+                Layout::Bsph,
+                Layout::Tall,
+                Layout::Wide,
+                Layout::Grid,
+                Layout::Columns,
+                Layout::CenteredMaster,
+                Layout::Spiral,
+            ],
+            // Layout and quota of workspaces when starting, e.g.
+            // `("5".to_string(), WorkspaceDefaults { layout: Layout::Monocle, quota: 0.5 })`
+            // (workspaces not listed use the first layout of `layouts`)
+            workspaces_defaults: vec![].into_iter().collect(),
             // Won't tile windows with this WM_CLASS
             ignore_classes: vec!["xscreensaver", "Discover-overlay"]
                 .into_iter()
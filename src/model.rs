@@ -1,5 +1,8 @@
+use crate::ipc::IpcSubscriber;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::Instant;
 use xcb::ModMask;
 pub use xcb::{
     MOD_MASK_1, MOD_MASK_2, MOD_MASK_3, MOD_MASK_4, MOD_MASK_5, MOD_MASK_CONTROL, MOD_MASK_SHIFT,
@@ -37,6 +40,13 @@ pub struct NormalHints {
     pub max_aspect: (u32, u32),
 }
 
+/// The fields of `WM_HINTS` umberwm cares about.
+#[derive(Debug)]
+pub struct WmHints {
+    pub input: Option<bool>,
+    pub urgent: bool,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
 pub enum Events {
     OnChangeWorkspace,
@@ -47,6 +57,9 @@ pub enum Events {
     OnChangeLayout,
     OnToggleGap,
     OnQuit,
+    OnUrgent,
+    OnUnresponsive,
+    OnSendToWorkspace,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -61,6 +74,24 @@ pub enum Actions {
     IncreaseQuota,
     DecreaseQuota,
     Quit,
+    ToggleFullscreen,
+    FocusUrgent,
+    KillWindow,
+    ToggleFloat,
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+    SwapLeft,
+    SwapRight,
+    SwapUp,
+    SwapDown,
+    SendToWorkspace(WorkspaceName),
+    IncreaseMasterCount,
+    DecreaseMasterCount,
+    PromoteToMaster,
+    PreviousLayout,
+    SetLayout(Layout),
 }
 
 pub enum Meta {
@@ -73,6 +104,22 @@ pub enum Layout {
     Bspv,
     Monocle,
     Bsph,
+    Tall,
+    Wide,
+    Grid,
+    Columns,
+    CenteredMaster,
+    Spiral,
+    /// a layout registered in `Conf::custom_layouts` under this name
+    Custom(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
 }
 
 pub type Window = u32;
@@ -83,9 +130,23 @@ pub type WorkspaceName = Key;
 
 pub type CustomAction = Box<dyn Fn()>;
 
+/// A layout implemented outside of umberwm, registered in `Conf::custom_layouts`.
+pub trait CustomLayout {
+    /// Returns the geometry of each of the tiled `windows`, in order, given the `area` of the
+    /// display they can use, the index of the focused window in `windows` if it is tiled, and
+    /// the quota of the workspace.
+    fn geometries(
+        &self,
+        area: &Geometry,
+        windows: &[Window],
+        focus: Option<usize>,
+        quota: f32,
+    ) -> Vec<Geometry>;
+}
+
 pub type Color = u32;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Geometry(pub u32, pub u32, pub u32, pub u32);
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -93,6 +154,19 @@ pub struct WindowBorder {
     pub width: u32,
     pub focus_color: Color,
     pub normal_color: Color,
+    #[serde(default = "default_urgent_color")]
+    pub urgent_color: Color,
+    /// color of the tile a dragged window would be swapped with
+    #[serde(default = "default_drop_target_color")]
+    pub drop_target_color: Color,
+}
+
+fn default_urgent_color() -> Color {
+    0xff0000
+}
+
+fn default_drop_target_color() -> Color {
+    0x00ff00
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -101,6 +175,46 @@ pub struct Workspace {
     pub windows: Vec<Window>,
     pub focus: usize,
     pub quota: f32,
+    /// number of master windows in the tall and wide layouts
+    #[serde(default = "default_master_count")]
+    pub master_count: usize,
+    /// splits of the tiled windows in the bspv and bsph layouts
+    #[serde(default)]
+    pub tree: Option<SplitNode>,
+}
+
+/// Node of the split tree of a workspace, whose leaves are the tiled windows in order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SplitNode {
+    Window(Window),
+    /// `ratio` of the area goes to `first`, the rest to `second`, the direction of the split
+    /// alternating with depth as in `geometries_bsp`
+    Split {
+        ratio: f32,
+        first: Box<SplitNode>,
+        second: Box<SplitNode>,
+    },
+}
+
+fn default_master_count() -> usize {
+    1
+}
+
+/// Space reserved by a dock on the edges of the screen, as published in `_NET_WM_STRUT_PARTIAL`.
+#[derive(Clone, Debug, Default)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+    pub left_start_y: u32,
+    pub left_end_y: u32,
+    pub right_start_y: u32,
+    pub right_end_y: u32,
+    pub top_start_x: u32,
+    pub top_end_x: u32,
+    pub bottom_start_x: u32,
+    pub bottom_end_x: u32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -146,11 +260,40 @@ pub struct SerializableConf {
     pub custom_commands: HashMap<Keybind, Vec<String>>,
     /// callback commands to be called on events
     pub command_callbacks: HashMap<Events, Vec<String>>,
+    /// layouts ChangeLayout and PreviousLayout cycle through, in order
+    #[serde(default = "default_layouts")]
+    pub layouts: Vec<Layout>,
+    /// layout and quota of the workspaces when umberwm starts without a saved state
+    #[serde(default)]
+    pub workspaces_defaults: HashMap<WorkspaceName, WorkspaceDefaults>,
+}
+
+fn default_layouts() -> Vec<Layout> {
+    vec![
+        Layout::Bspv,
+        Layout::Monocle,
+        Layout::Bsph,
+        Layout::Tall,
+        Layout::Wide,
+        Layout::Grid,
+        Layout::Columns,
+        Layout::CenteredMaster,
+        Layout::Spiral,
+    ]
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WorkspaceDefaults {
+    pub layout: Layout,
+    /// share of the screen given to the first window(s), clamped between 0.1 and 0.9
+    pub quota: f32,
 }
 
 pub struct Conf {
     pub serializable: SerializableConf,
     pub custom_actions: HashMap<Keybind, CustomAction>,
+    /// layouts usable as `Layout::Custom` with their name
+    pub custom_layouts: HashMap<String, Box<dyn CustomLayout>>,
     pub events_callbacks: EventsCallbacks,
 }
 
@@ -158,10 +301,65 @@ pub struct Conf {
 pub struct SerializableState {
     pub float_windows: Vec<Window>,
     pub overlay_windows: Vec<Window>,
+    #[serde(default)]
+    pub fullscreen_windows: Vec<Window>,
+    /// transient windows (e.g. dialogs) and the window they belong to
+    #[serde(default)]
+    pub transients: HashMap<Window, Window>,
+    /// last geometry of windows which floated
+    #[serde(default)]
+    pub float_geometries: HashMap<Window, Geometry>,
     pub workspaces: HashMap<WorkspaceName, Workspace>,
     pub current_workspace: WorkspaceName,
 }
 
+/// A managed window, as reported by IPC queries.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub id: Window,
+    pub instance: String,
+    pub class: String,
+    pub title: String,
+    pub workspace: Option<WorkspaceName>,
+    pub floating: bool,
+    pub focused: bool,
+}
+
+/// A workspace and the display it belongs to, as reported by IPC queries.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    pub name: WorkspaceName,
+    pub display: DisplayId,
+    pub current: bool,
+    pub workspace: Workspace,
+    /// the windows of the workspace, in order, with their class and title
+    pub windows: Vec<WindowSnapshot>,
+}
+
+/// The whole window manager state, as reported by IPC queries.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    pub state: SerializableState,
+    pub windows: Vec<WindowSnapshot>,
+}
+
+/// A display geometry and the workspaces assigned to it, as reported by IPC queries.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisplaySnapshot {
+    pub id: DisplayId,
+    pub geometry: Geometry,
+    pub border: DisplayBorder,
+    pub workspaces: Vec<WorkspaceName>,
+}
+
+/// An event sent to IPC subscribers, with the values substituted in `command_callbacks` (e.g.
+/// `window_id` for `%window_id%`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventMessage {
+    pub event: Events,
+    pub values: HashMap<String, String>,
+}
+
 #[derive(Clone)]
 pub struct MouseMoveStart {
     pub root_x: i16,
@@ -170,9 +368,21 @@ pub struct MouseMoveStart {
     pub detail: u8,
 }
 
+/// The split, or workspace quota, being moved by dragging a tiled window.
+#[derive(Clone)]
+pub struct TiledResizeStart {
+    pub window: Window,
+    pub ratio: f32,
+    /// length in pixels of the area shared by the split
+    pub length: u32,
+    /// whether the split follows the pointer horizontally
+    pub along_x: bool,
+}
+
 pub struct GeometryInfo {
     pub vertical: usize,
     pub quota: f32,
+    pub master_count: usize,
 }
 
 pub struct UmberWm {
@@ -180,12 +390,31 @@ pub struct UmberWm {
     pub current_workspace: WorkspaceName,
     pub float_windows: Vec<Window>,
     pub overlay_windows: Vec<Window>,
+    pub fullscreen_windows: Vec<Window>,
+    pub float_geometries: HashMap<Window, Geometry>,
+    pub docks: HashMap<Window, Strut>,
+    pub transients: HashMap<Window, Window>,
+    pub urgent_windows: Vec<Window>,
+    pub pending_pings: HashMap<Window, Instant>,
+    /// timestamp of the last event carrying one, ICCCM requiring a real one to change focus
+    pub last_event_time: xcb::Timestamp,
     pub workspaces: HashMap<WorkspaceName, Workspace>,
     pub conn: xcb::Connection,
     pub mouse_move_start: Option<MouseMoveStart>,
+    pub tiled_resize_start: Option<TiledResizeStart>,
+    pub drop_target: Option<Window>,
     pub button_press_geometry: Option<Geometry>,
     pub xmodmap_pke: XmodmapPke,
     pub displays_geometries: Vec<Geometry>,
+    /// width and height of the whole screen, updated on RandR changes
+    pub screen_size: (u32, u32),
     pub randr_base: u8,
     pub previous_display: DisplayId,
+    /// last workspace switched to on each display
+    pub visible_workspaces: HashMap<DisplayId, WorkspaceName>,
+    pub ipc_listener: Option<UnixListener>,
+    /// connections whose command line has not been fully received yet
+    pub ipc_clients: Vec<(UnixStream, Vec<u8>)>,
+    pub ipc_subscribers: Vec<IpcSubscriber>,
+    pub check_window: Window,
 }
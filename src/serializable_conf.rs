@@ -1,4 +1,4 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::model::*;
 use ron::de::from_str;
 use ron::ser::{to_string_pretty, PrettyConfig};
@@ -30,7 +30,13 @@ impl SerializableConf {
         let mut file = File::open(umberwm_conf())?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        Ok(from_str(contents.as_str())?)
+        let conf: Self = from_str(contents.as_str())?;
+        for (name, defaults) in &conf.workspaces_defaults {
+            if !defaults.quota.is_finite() || defaults.quota <= 0.0 || defaults.quota >= 1.0 {
+                return Err(Error::InvalidQuota(name.clone(), defaults.quota).into());
+            }
+        }
+        Ok(conf)
     }
 
     pub fn exists() -> bool {
@@ -1,6 +1,7 @@
 use crate::error::{Error, Result};
 use crate::model::*;
 use ron::de::from_str;
+use std::collections::HashMap;
 use std::fs::remove_file;
 use std::fs::File;
 use std::io::prelude::*;
@@ -20,6 +21,9 @@ pub fn load_serializable_state(conf: &Conf) -> Result<SerializableState> {
         Ok(SerializableState {
             float_windows: vec![],
             overlay_windows: vec![],
+            fullscreen_windows: vec![],
+            transients: HashMap::new(),
+            float_geometries: HashMap::new(),
             workspaces: conf
                 .serializable
                 .workspaces_names
@@ -28,13 +32,30 @@ pub fn load_serializable_state(conf: &Conf) -> Result<SerializableState> {
                 .flatten()
                 .into_iter()
                 .map(|x| {
+                    let (layout, quota) = match conf.serializable.workspaces_defaults.get(&x) {
+                        // the layouts expect a quota leaving room for the other windows
+                        Some(defaults) if defaults.quota.is_finite() => {
+                            (defaults.layout.clone(), defaults.quota.clamp(0.1, 0.9))
+                        }
+                        Some(defaults) => (defaults.layout.clone(), 0.5),
+                        None => (
+                            conf.serializable
+                                .layouts
+                                .first()
+                                .cloned()
+                                .unwrap_or(Layout::Bspv),
+                            0.5,
+                        ),
+                    };
                     (
                         x,
                         Workspace {
-                            layout: Layout::Bspv,
+                            layout,
                             windows: vec![],
                             focus: 0,
-                            quota: 0.5,
+                            quota,
+                            master_count: 1,
+                            tree: None,
                         },
                     )
                 })
@@ -0,0 +1,288 @@
+use crate::model::{Layout, SplitNode, Window, Workspace};
+
+impl SplitNode {
+    pub fn is_window(&self, window: Window) -> bool {
+        matches!(self, SplitNode::Window(x) if *x == window)
+    }
+
+    pub fn windows(&self) -> Vec<Window> {
+        match self {
+            SplitNode::Window(window) => vec![*window],
+            SplitNode::Split { first, second, .. } => {
+                let mut res = first.windows();
+                res.append(&mut second.windows());
+                res
+            }
+        }
+    }
+
+    /// Removes the windows which are not in `windows`, their sibling taking the place of their
+    /// parent split.
+    fn retain(self, windows: &[Window]) -> Option<SplitNode> {
+        match self {
+            SplitNode::Window(window) if windows.contains(&window) => Some(self),
+            SplitNode::Window(_) => None,
+            SplitNode::Split {
+                ratio,
+                first,
+                second,
+            } => match (first.retain(windows), second.retain(windows)) {
+                (Some(first), Some(second)) => Some(SplitNode::Split {
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            },
+        }
+    }
+
+    /// Splits the node of `window` between it and `new_window`, which comes first if
+    /// `new_first`.
+    fn split(&mut self, window: Window, new_window: Window, ratio: f32, new_first: bool) -> bool {
+        match self {
+            SplitNode::Window(x) if *x == window => {
+                let (first, second) = if new_first {
+                    (new_window, window)
+                } else {
+                    (window, new_window)
+                };
+                *self = SplitNode::Split {
+                    ratio,
+                    first: Box::new(SplitNode::Window(first)),
+                    second: Box::new(SplitNode::Window(second)),
+                };
+                true
+            }
+            SplitNode::Window(_) => false,
+            SplitNode::Split { first, second, .. } => {
+                first.split(window, new_window, ratio, new_first)
+                    || second.split(window, new_window, ratio, new_first)
+            }
+        }
+    }
+
+    /// Assigns the windows to the leaves, in order.
+    fn relabel(&mut self, windows: &mut impl Iterator<Item = Window>) {
+        match self {
+            SplitNode::Window(window) => {
+                if let Some(next) = windows.next() {
+                    *window = next;
+                }
+            }
+            SplitNode::Split { first, second, .. } => {
+                first.relabel(windows);
+                second.relabel(windows);
+            }
+        }
+    }
+
+    /// Returns the ratio of the split directly containing the window.
+    pub fn split_ratio(&self, window: Window) -> Option<f32> {
+        match self {
+            SplitNode::Window(_) => None,
+            SplitNode::Split {
+                ratio,
+                first,
+                second,
+            } => {
+                if first.is_window(window) || second.is_window(window) {
+                    Some(*ratio)
+                } else {
+                    first
+                        .split_ratio(window)
+                        .or_else(|| second.split_ratio(window))
+                }
+            }
+        }
+    }
+
+    /// Sets the ratio of the split directly containing the window.
+    pub fn set_split_ratio(&mut self, window: Window, new_ratio: f32) -> bool {
+        match self {
+            SplitNode::Window(_) => false,
+            SplitNode::Split {
+                ratio,
+                first,
+                second,
+            } => {
+                if first.is_window(window) || second.is_window(window) {
+                    *ratio = new_ratio;
+                    true
+                } else {
+                    first.set_split_ratio(window, new_ratio)
+                        || second.set_split_ratio(window, new_ratio)
+                }
+            }
+        }
+    }
+
+    /// Grows (or shrinks if `delta` is negative) the window by moving the split it belongs to.
+    /// Returns false if the window is not in a split.
+    pub fn resize_window(&mut self, window: Window, delta: f32) -> bool {
+        match self {
+            SplitNode::Window(_) => false,
+            SplitNode::Split {
+                ratio,
+                first,
+                second,
+            } => {
+                let delta = if first.is_window(window) {
+                    delta
+                } else if second.is_window(window) {
+                    -delta
+                } else {
+                    return first.resize_window(window, delta)
+                        || second.resize_window(window, delta);
+                };
+                *ratio = (*ratio + delta).clamp(0.1, 0.9);
+                true
+            }
+        }
+    }
+}
+
+/// Updates the split tree of a workspace so that its leaves are `windows`, in order.
+///
+/// Windows which are not in the tree yet split the node of the window preceding them in
+/// `windows` (`ratio` being the share of the preceding window), and windows whose order changed
+/// (e.g. swapped windows) exchange their nodes.
+pub fn sync_tree(tree: Option<SplitNode>, windows: &[Window], ratio: f32) -> Option<SplitNode> {
+    let mut tree = tree.and_then(|tree| tree.retain(windows));
+    for (i, window) in windows.iter().enumerate() {
+        let node = match tree.as_mut() {
+            Some(node) => node,
+            None => {
+                tree = Some(SplitNode::Window(*window));
+                continue;
+            }
+        };
+        if node.windows().contains(window) {
+            continue;
+        }
+        if i == 0 {
+            let first = node.windows()[0];
+            node.split(first, *window, 1.0 - ratio, true);
+        } else {
+            node.split(windows[i - 1], *window, ratio, false);
+        }
+    }
+    if let Some(node) = tree.as_mut() {
+        node.relabel(&mut windows.iter().copied());
+    }
+    tree
+}
+
+/// In the bsp layouts, grows (or shrinks) the focused window by moving its own split only.
+/// Returns false if the workspace quota should be used instead.
+pub fn resize_focused_split(workspace: &mut Workspace, delta: f32) -> bool {
+    match (&workspace.layout, &mut workspace.tree) {
+        (Layout::Bspv, Some(tree)) | (Layout::Bsph, Some(tree)) => {
+            match workspace.windows.get(workspace.focus) {
+                Some(window) => tree.resize_window(*window, delta),
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(ratio: f32, first: SplitNode, second: SplitNode) -> SplitNode {
+        SplitNode::Split {
+            ratio,
+            first: Box::new(first),
+            second: Box::new(second),
+        }
+    }
+
+    #[test]
+    fn sync_tree_builds_a_tree_for_new_windows() {
+        assert_eq!(sync_tree(None, &[], 0.5), None);
+        assert_eq!(
+            sync_tree(None, &[1, 2], 0.6),
+            Some(split(0.6, SplitNode::Window(1), SplitNode::Window(2)))
+        );
+    }
+
+    #[test]
+    fn sync_tree_splits_the_preceding_window() {
+        // 3 was inserted after the focused window 1
+        let tree = split(0.3, SplitNode::Window(1), SplitNode::Window(2));
+        assert_eq!(
+            sync_tree(Some(tree), &[1, 3, 2], 0.5),
+            Some(split(
+                0.3,
+                split(0.5, SplitNode::Window(1), SplitNode::Window(3)),
+                SplitNode::Window(2)
+            ))
+        );
+    }
+
+    #[test]
+    fn sync_tree_splits_the_first_window_for_a_window_at_index_0() {
+        let tree = split(0.3, SplitNode::Window(1), SplitNode::Window(2));
+        assert_eq!(
+            sync_tree(Some(tree), &[3, 1, 2], 0.75),
+            Some(split(
+                0.3,
+                split(0.25, SplitNode::Window(3), SplitNode::Window(1)),
+                SplitNode::Window(2)
+            ))
+        );
+    }
+
+    #[test]
+    fn sync_tree_relabels_swapped_windows() {
+        let tree = split(
+            0.3,
+            SplitNode::Window(1),
+            split(0.7, SplitNode::Window(2), SplitNode::Window(3)),
+        );
+        assert_eq!(
+            sync_tree(Some(tree), &[3, 2, 1], 0.5),
+            Some(split(
+                0.3,
+                SplitNode::Window(3),
+                split(0.7, SplitNode::Window(2), SplitNode::Window(1))
+            ))
+        );
+    }
+
+    #[test]
+    fn sync_tree_gives_the_place_of_removed_windows_to_their_sibling() {
+        let tree = split(
+            0.3,
+            SplitNode::Window(1),
+            split(0.7, SplitNode::Window(2), SplitNode::Window(3)),
+        );
+        assert_eq!(
+            sync_tree(Some(tree.clone()), &[1, 3], 0.5),
+            Some(split(0.3, SplitNode::Window(1), SplitNode::Window(3)))
+        );
+        assert_eq!(
+            sync_tree(Some(tree.clone()), &[2], 0.5),
+            Some(SplitNode::Window(2))
+        );
+        assert_eq!(sync_tree(Some(tree), &[], 0.5), None);
+    }
+
+    #[test]
+    fn resize_window_moves_the_split_of_the_window_only() {
+        let mut tree = split(
+            0.5,
+            SplitNode::Window(1),
+            split(0.5, SplitNode::Window(2), SplitNode::Window(3)),
+        );
+        assert!(tree.resize_window(3, 0.1));
+        assert_eq!(tree.split_ratio(1), Some(0.5));
+        assert_eq!(tree.split_ratio(3), Some(0.4));
+        assert!(tree.resize_window(1, 0.9));
+        assert_eq!(tree.split_ratio(1), Some(0.9));
+        assert!(!SplitNode::Window(1).resize_window(1, 0.1));
+    }
+}
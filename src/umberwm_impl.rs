@@ -1,34 +1,90 @@
 use crate::error::{Error, LogError, Result};
-use crate::geometries::geometries_bsp;
+use crate::geometries::{
+    geometries_bsp, geometries_centered_master, geometries_columns, geometries_grid,
+    geometries_master, geometries_spiral, geometries_tree, merge_borders, nearest_in_direction,
+    split_area, strut_border,
+};
+mod ewmh;
 mod helpers;
+use crate::ipc::{self, IpcCommand, IpcQuery, IpcSubscriber};
 use crate::keycode;
 use crate::model::*;
 mod resize;
 use crate::serializable_state::UMBERWM_STATE;
+use crate::split_tree::{resize_focused_split, sync_tree};
+use ewmh::{
+    set_cardinal_property, set_desktop_names, set_window_list_property, setup_supporting_wm_check,
+};
 use helpers::{
-    change_workspace, get_atom_property, get_display_border, get_displays_geometries,
-    get_str_property, is_firefox_drag_n_drop_initialization_window, run_command,
-    window_types_from_list,
+    center_window_over, change_workspace, configure_request_values, event_time, get_atom,
+    get_atom_property, get_atoms_property, get_cardinal_property, get_display_border,
+    get_displays_geometries, get_root, get_screen_size, get_str_property, get_strut,
+    get_transient_for, get_window_title, get_wm_hints, get_wm_state,
+    is_firefox_drag_n_drop_initialization_window, run_command, send_configure_notify,
+    send_wm_protocol, set_wm_state, window_types_from_list, WM_STATE_ICONIC, WM_STATE_NORMAL,
+    WM_STATE_WITHDRAWN,
 };
 use resize::{resize_bsp, resize_monocle};
 use ron::ser::to_string;
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::fs::File;
 use std::io::prelude::*;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
 use xcb::randr;
 use xcb::xproto;
 
+/// How long a window being closed has to answer `_NET_WM_PING`.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
 fn layout_to_string(layout: &Layout) -> String {
     match layout {
         Layout::Bspv => "bspv",
         Layout::Bsph => "bsph",
         Layout::Monocle => "monocle",
+        Layout::Tall => "tall",
+        Layout::Wide => "wide",
+        Layout::Grid => "grid",
+        Layout::Columns => "columns",
+        Layout::CenteredMaster => "centered_master",
+        Layout::Spiral => "spiral",
+        Layout::Custom(name) => name,
     }
     .to_string()
 }
 
+/// Returns the layout following (or preceding) `layout` in `layouts`, or the first one if
+/// `layout` is not part of the cycle.
+fn cycle_layout(layouts: &[Layout], layout: &Layout, forward: bool) -> Layout {
+    let count = layouts.len();
+    match layouts.iter().position(|x| x == layout) {
+        Some(i) if forward => layouts[(i + 1) % count].clone(),
+        Some(i) => layouts[(i + count - 1) % count].clone(),
+        None => layouts.first().cloned().unwrap_or_else(|| layout.clone()),
+    }
+}
+
 impl UmberWm {
-    fn resize_workspace_windows(&mut self, workspace: &Workspace, mut display: usize) {
+    fn resize_workspace_windows(&mut self, workspace_name: &str, display: usize) {
+        let float_windows = &self.float_windows;
+        let workspace = match self.workspaces.get_mut(workspace_name) {
+            Some(workspace) => workspace,
+            None => return,
+        };
+        let tiled_windows: Vec<Window> = workspace
+            .windows
+            .iter()
+            .filter(|window| !float_windows.contains(window))
+            .copied()
+            .collect();
+        workspace.tree = sync_tree(workspace.tree.take(), &tiled_windows, workspace.quota);
+        let workspace = workspace.clone();
+        self.tile_workspace_windows(&workspace, display);
+        self.resize_fullscreen_windows(&workspace, display);
+    }
+
+    fn tile_workspace_windows(&mut self, workspace: &Workspace, mut display: usize) {
         let mut non_float_windows = workspace.windows.clone();
         non_float_windows.retain(|w| !self.float_windows.contains(w));
         let count = non_float_windows.len();
@@ -38,20 +94,101 @@ impl UmberWm {
         if display >= self.displays_geometries.len() {
             display = self.displays_geometries.len() - 1;
         }
-        let display_border = get_display_border(&self.conf.serializable.display_borders, display);
-        let display_geometry = self.displays_geometries.get(display).unwrap();
-        let width = display_geometry.2 as u32 - display_border.right - display_border.left;
-        let height = display_geometry.3 as u32 - display_border.top - display_border.bottom;
-        let left = display_geometry.0 + display_border.left;
-        let top = display_geometry.1 + display_border.top;
+        let display_border = self.display_border(display);
         let gap = if self.conf.serializable.with_gap {
             display_border.gap
         } else {
             0
         };
-        let geos = match workspace.layout {
-            Layout::Bspv => geometries_bsp(
+        let geos = self.layout_geometries(workspace, &non_float_windows, display);
+        match workspace.layout {
+            Layout::Bspv
+            | Layout::Bsph
+            | Layout::Tall
+            | Layout::Wide
+            | Layout::Grid
+            | Layout::Columns
+            | Layout::CenteredMaster
+            | Layout::Spiral
+            | Layout::Custom(_) => {
+                let (tiled_windows, geos): (Vec<Window>, Vec<Geometry>) = non_float_windows
+                    .into_iter()
+                    .zip(geos)
+                    .filter(|(window, _)| !self.fullscreen_windows.contains(window))
+                    .unzip();
+                resize_bsp(
+                    &self.conn,
+                    self.conf.serializable.border.width,
+                    tiled_windows,
+                    geos,
+                    gap,
+                )
+            }
+            Layout::Monocle => resize_monocle(
+                &self.conn,
+                self.conf.serializable.border.width,
+                workspace
+                    .windows
+                    .get(workspace.focus)
+                    .filter(|window| !self.fullscreen_windows.contains(window)),
+                geos,
+                gap,
+            ),
+        }
+        for (i, window) in workspace.windows.iter().enumerate() {
+            self.focus_unfocus(window, i == workspace.focus).log();
+        }
+        for overlay_window in &self.overlay_windows {
+            xcb::configure_window(
+                &self.conn,
+                *overlay_window,
+                &[(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)],
+            );
+        }
+    }
+
+    /// Returns the geometries of the tiled windows of the workspace, in order, as computed by its
+    /// layout. In monocle, only the first geometry is returned.
+    fn layout_geometries(
+        &self,
+        workspace: &Workspace,
+        non_float_windows: &[Window],
+        display: DisplayId,
+    ) -> Vec<Geometry> {
+        let count = non_float_windows.len();
+        let Geometry(left, top, width, height) = self.tiling_area(display);
+        match workspace.layout {
+            // the tree is synced with the tiled windows in `resize_workspace_windows`
+            Layout::Bspv | Layout::Bsph => match &workspace.tree {
+                Some(tree) => geometries_tree(
+                    tree,
+                    0,
+                    left,
+                    top,
+                    width,
+                    height,
+                    if workspace.layout == Layout::Bspv {
+                        1
+                    } else {
+                        0
+                    },
+                ),
+                None => vec![],
+            },
+            Layout::Monocle => geometries_bsp(
                 0,
+                1,
+                left,
+                top,
+                width,
+                height,
+                &GeometryInfo {
+                    vertical: 1,
+                    quota: workspace.quota,
+                    master_count: workspace.master_count,
+                },
+            ),
+            Layout::Tall => geometries_master(
                 count,
                 left,
                 top,
@@ -60,10 +197,10 @@ impl UmberWm {
                 &GeometryInfo {
                     vertical: 1,
                     quota: workspace.quota,
+                    master_count: workspace.master_count,
                 },
             ),
-            Layout::Bsph => geometries_bsp(
-                0,
+            Layout::Wide => geometries_master(
                 count,
                 left,
                 top,
@@ -72,11 +209,12 @@ impl UmberWm {
                 &GeometryInfo {
                     vertical: 0,
                     quota: workspace.quota,
+                    master_count: workspace.master_count,
                 },
             ),
-            Layout::Monocle => geometries_bsp(
+            Layout::Spiral => geometries_spiral(
                 0,
-                1,
+                count,
                 left,
                 top,
                 width,
@@ -84,46 +222,371 @@ impl UmberWm {
                 &GeometryInfo {
                     vertical: 1,
                     quota: workspace.quota,
+                    master_count: workspace.master_count,
                 },
             ),
+            Layout::Custom(ref name) => match self.conf.custom_layouts.get(name) {
+                Some(custom_layout) => custom_layout.geometries(
+                    &Geometry(left, top, width, height),
+                    non_float_windows,
+                    workspace
+                        .windows
+                        .get(workspace.focus)
+                        .and_then(|focused| non_float_windows.iter().position(|x| x == focused)),
+                    workspace.quota,
+                ),
+                // the layout is not registered (anymore), tile as bspv
+                None => geometries_bsp(
+                    0,
+                    count,
+                    left,
+                    top,
+                    width,
+                    height,
+                    &GeometryInfo {
+                        vertical: 1,
+                        quota: workspace.quota,
+                        master_count: workspace.master_count,
+                    },
+                ),
+            },
+            Layout::Grid => geometries_grid(count, left, top, width, height),
+            Layout::Columns => geometries_columns(count, left, top, width, height),
+            Layout::CenteredMaster => geometries_centered_master(
+                count,
+                left,
+                top,
+                width,
+                height,
+                &GeometryInfo {
+                    vertical: 1,
+                    quota: workspace.quota,
+                    master_count: workspace.master_count,
+                },
+            ),
+        }
+    }
+
+    /// Returns the area of the display in which windows are tiled.
+    fn tiling_area(&self, display: DisplayId) -> Geometry {
+        let display_border = self.display_border(display);
+        let display_geometry = &self.displays_geometries[display];
+        Geometry(
+            display_geometry.0 + display_border.left,
+            display_geometry.1 + display_border.top,
+            display_geometry
+                .2
+                .saturating_sub(display_border.left + display_border.right),
+            display_geometry
+                .3
+                .saturating_sub(display_border.top + display_border.bottom),
+        )
+    }
+
+    /// Returns the display showing the workspace, or the last display if there are less
+    /// displays than configured.
+    fn workspace_display(&self, workspace: &str) -> DisplayId {
+        let display = self
+            .conf
+            .serializable
+            .workspaces_names
+            .iter()
+            .position(|names| names.iter().any(|name| name == workspace))
+            .unwrap_or(0);
+        min(display, self.displays_geometries.len().saturating_sub(1))
+    }
+
+    /// Returns the configured border of the display, enlarged to leave room for docks.
+    fn display_border(&self, display: DisplayId) -> DisplayBorder {
+        let mut border = get_display_border(&self.conf.serializable.display_borders, display);
+        if let Some(display_geometry) = self.displays_geometries.get(display) {
+            let (screen_width, screen_height) = self.screen_size;
+            for strut in self.docks.values() {
+                let reserved = strut_border(strut, display_geometry, screen_width, screen_height);
+                border = merge_borders(&border, &reserved);
+            }
+        }
+        border
+    }
+
+    fn add_dock(&mut self, window: Window) -> Result<()> {
+        xcb::change_window_attributes(
+            &self.conn,
+            window,
+            &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)],
+        );
+        let strut = get_strut(&self.conn, window)?.unwrap_or_default();
+        self.docks.insert(window, strut);
+        self.update_work_area()
+    }
+
+    /// Publishes `_NET_WORKAREA` for each desktop and lays out the visible workspaces again,
+    /// after docks or displays changed.
+    fn update_work_area(&mut self) -> Result<()> {
+        let mut work_area = vec![];
+        for (display, workspaces_names) in
+            self.conf.serializable.workspaces_names.iter().enumerate()
+        {
+            let display = display.min(self.displays_geometries.len().saturating_sub(1));
+            if let Some(geometry) = self.displays_geometries.get(display) {
+                let border = self.display_border(display);
+                for _ in workspaces_names {
+                    work_area.extend_from_slice(&[
+                        geometry.0 + border.left,
+                        geometry.1 + border.top,
+                        geometry.2.saturating_sub(border.left + border.right),
+                        geometry.3.saturating_sub(border.top + border.bottom),
+                    ]);
+                }
+            }
+        }
+        let root = get_root(&self.conn)?;
+        set_cardinal_property(&self.conn, root, "_NET_WORKAREA", &work_area)?;
+        for display in 0..self.displays_geometries.len() {
+            if let Some(workspace) = self.visible_workspace(display) {
+                if workspace != self.current_workspace && self.workspaces.contains_key(&workspace) {
+                    self.resize_workspace_windows(&workspace, self.workspace_display(&workspace));
+                }
+            }
+        }
+        self.resize_current_workspace()
+    }
+
+    fn handle_property_notify(&mut self, event: &xcb::PropertyNotifyEvent) -> Result<()> {
+        let window = event.window();
+        let atom = event.atom();
+        if self.docks.contains_key(&window)
+            && (atom == get_atom(&self.conn, "_NET_WM_STRUT_PARTIAL")?
+                || atom == get_atom(&self.conn, "_NET_WM_STRUT")?)
+        {
+            let strut = get_strut(&self.conn, window)?.unwrap_or_default();
+            self.docks.insert(window, strut);
+            self.update_work_area()?;
+        } else if atom == xproto::ATOM_WM_HINTS && self.workspace_of_window(window).is_some() {
+            if let Some(hints) = get_wm_hints(&self.conn, window)? {
+                self.set_urgent(window, hints.urgent)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes fullscreen windows cover their whole display, above overlays.
+    fn resize_fullscreen_windows(&self, workspace: &Workspace, display: usize) {
+        let display_geometry = match self
+            .displays_geometries
+            .get(display)
+            .or_else(|| self.displays_geometries.last())
+        {
+            Some(display_geometry) => display_geometry,
+            None => return,
         };
-        match workspace.layout {
-            Layout::Bspv | Layout::Bsph => resize_bsp(
-                &self.conn,
-                self.conf.serializable.border.width,
-                non_float_windows,
-                geos,
-                gap,
+        for window in &workspace.windows {
+            if self.fullscreen_windows.contains(window) {
+                xcb::configure_window(
+                    &self.conn,
+                    *window,
+                    &[
+                        (xcb::CONFIG_WINDOW_X as u16, display_geometry.0),
+                        (xcb::CONFIG_WINDOW_Y as u16, display_geometry.1),
+                        (xcb::CONFIG_WINDOW_WIDTH as u16, display_geometry.2),
+                        (xcb::CONFIG_WINDOW_HEIGHT as u16, display_geometry.3),
+                        (xcb::CONFIG_WINDOW_BORDER_WIDTH as u16, 0),
+                        (xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE),
+                    ],
+                );
+            }
+        }
+    }
+
+    fn set_fullscreen(&mut self, window: Window, fullscreen: bool) -> Result<()> {
+        if fullscreen == self.fullscreen_windows.contains(&window) {
+            return Ok(());
+        }
+        let floating = self.float_windows.contains(&window);
+        if fullscreen {
+            if floating {
+                self.save_float_geometry(window)?;
+            }
+            self.fullscreen_windows.push(window);
+        } else {
+            self.fullscreen_windows.retain(|x| *x != window);
+            if floating {
+                self.restore_float_geometry(window);
+            }
+        }
+        self.update_net_wm_state(window)
+    }
+
+    /// Remembers the geometry of a floating window, to restore it when it floats again.
+    fn save_float_geometry(&mut self, window: Window) -> Result<()> {
+        let geometry = xcb::get_geometry(&self.conn, window).get_reply()?;
+        self.float_geometries.insert(
+            window,
+            Geometry(
+                geometry.x() as u32,
+                geometry.y() as u32,
+                geometry.width() as u32,
+                geometry.height() as u32,
             ),
-            Layout::Monocle => resize_monocle(
+        );
+        Ok(())
+    }
+
+    fn restore_float_geometry(&self, window: Window) {
+        if let Some(geometry) = self.float_geometries.get(&window) {
+            xcb::configure_window(
                 &self.conn,
-                self.conf.serializable.border.width,
-                workspace,
-                geos,
-                gap,
-            ),
-        }
-        for (i, window) in workspace.windows.iter().enumerate() {
-            self.focus_unfocus(window, i == workspace.focus).log();
+                window,
+                &[
+                    (xcb::CONFIG_WINDOW_X as u16, geometry.0),
+                    (xcb::CONFIG_WINDOW_Y as u16, geometry.1),
+                    (xcb::CONFIG_WINDOW_WIDTH as u16, geometry.2),
+                    (xcb::CONFIG_WINDOW_HEIGHT as u16, geometry.3),
+                    (
+                        xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
+                        self.conf.serializable.border.width,
+                    ),
+                ],
+            );
         }
-        for overlay_window in &self.overlay_windows {
+    }
+
+    /// Floats a tiled window at its remembered floating geometry, or tiles a floating window.
+    fn toggle_float(&mut self, window: Window) -> Result<()> {
+        if self.float_windows.contains(&window) {
+            self.save_float_geometry(window)?;
+            self.float_windows.retain(|x| *x != window);
+        } else {
+            self.float_windows.push(window);
+            self.restore_float_geometry(window);
             xcb::configure_window(
                 &self.conn,
-                *overlay_window,
+                window,
                 &[(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)],
             );
         }
+        Ok(())
+    }
+
+    /// Publishes the `_NET_WM_STATE` of the window.
+    fn update_net_wm_state(&self, window: Window) -> Result<()> {
+        let mut states = vec![];
+        if self.fullscreen_windows.contains(&window) {
+            states.push(get_atom(&self.conn, "_NET_WM_STATE_FULLSCREEN")?);
+        }
+        if self.urgent_windows.contains(&window) {
+            states.push(get_atom(&self.conn, "_NET_WM_STATE_DEMANDS_ATTENTION")?);
+        }
+        xproto::change_property(
+            &self.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            get_atom(&self.conn, "_NET_WM_STATE")?,
+            xproto::ATOM_ATOM,
+            32,
+            &states[..],
+        );
+        Ok(())
+    }
+
+    /// Handles a `_NET_WM_STATE` client message, which adds (1), removes (0) or toggles (2) up to
+    /// two states.
+    fn handle_net_wm_state_message(&mut self, window: Window, data: &[u32]) -> Result<()> {
+        if self.workspace_of_window(window).is_none() {
+            return self.update_unmanaged_net_wm_state(window, data);
+        }
+        let fullscreen_atom = get_atom(&self.conn, "_NET_WM_STATE_FULLSCREEN")?;
+        if data[1] == fullscreen_atom || data[2] == fullscreen_atom {
+            let fullscreen = match data[0] {
+                0 => false,
+                1 => true,
+                _ => !self.fullscreen_windows.contains(&window),
+            };
+            self.set_fullscreen(window, fullscreen)?;
+            self.resize_current_workspace()?;
+        }
+        let demands_attention_atom = get_atom(&self.conn, "_NET_WM_STATE_DEMANDS_ATTENTION")?;
+        if data[1] == demands_attention_atom || data[2] == demands_attention_atom {
+            let urgent = match data[0] {
+                0 => false,
+                1 => true,
+                _ => !self.urgent_windows.contains(&window),
+            };
+            self.set_urgent(window, urgent)?;
+        }
+        Ok(())
+    }
+
+    /// Windows which are not managed (yet) only get their `_NET_WM_STATE` property updated, so
+    /// that they start in the requested state if they are mapped later.
+    fn update_unmanaged_net_wm_state(&self, window: Window, data: &[u32]) -> Result<()> {
+        let mut states = get_atoms_property(&self.conn, window, "_NET_WM_STATE")?;
+        for atom in [data[1], data[2]] {
+            if atom == xcb::NONE {
+                continue;
+            }
+            let set = match data[0] {
+                0 => false,
+                1 => true,
+                _ => !states.contains(&atom),
+            };
+            states.retain(|x| *x != atom);
+            if set {
+                states.push(atom);
+            }
+        }
+        xproto::change_property(
+            &self.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            get_atom(&self.conn, "_NET_WM_STATE")?,
+            xproto::ATOM_ATOM,
+            32,
+            &states[..],
+        );
+        Ok(())
     }
 
     fn change_to_current_workspace(&mut self) {
         let key = self.current_workspace.to_string();
-        let mod_mask = self.conf.serializable.meta;
+        self.switch_to_workspace(key, false);
+    }
+
+    fn switch_to_workspace(&mut self, key: WorkspaceName, move_window: bool) {
+        let mod_mask = if move_window {
+            self.conf.serializable.meta | xcb::MOD_MASK_SHIFT
+        } else {
+            self.conf.serializable.meta
+        };
         self.handle_workspace_change(&Keybind { mod_mask, key });
     }
 
+    fn resize_current_workspace(&mut self) -> Result<()> {
+        let workspaces_names_by_display = self.conf.serializable.workspaces_names.clone();
+        for (display, workspaces_names) in workspaces_names_by_display.iter().enumerate() {
+            if workspaces_names.contains(&self.current_workspace) {
+                if !self.workspaces.contains_key(&self.current_workspace) {
+                    return Err(Error::WorkspaceNotFound);
+                }
+                let current_workspace = self.current_workspace.clone();
+                self.resize_workspace_windows(&current_workspace, display);
+            }
+        }
+        Ok(())
+    }
+
     pub fn init(&mut self) {
-        self.displays_geometries = get_displays_geometries(&self.conn).unwrap();
         let screen = self.conn.get_setup().roots().next().unwrap();
+        self.select_root_events(&screen);
+        self.ipc_listener = ipc::bind().log();
+        if let Some(check_window) = setup_supporting_wm_check(&self.conn, screen.root()).log() {
+            self.check_window = check_window;
+        }
+        self.displays_geometries = get_displays_geometries(&self.conn).unwrap();
+        self.screen_size = (
+            screen.width_in_pixels() as u32,
+            screen.height_in_pixels() as u32,
+        );
         self.randr_base = self
             .conn
             .get_extension_data(&mut randr::id())
@@ -155,16 +618,148 @@ impl UmberWm {
                 self.conf.serializable.meta as u16,
             );
         }
-        xcb::change_window_attributes(
+        for workspace in self.workspaces.values() {
+            for window in &workspace.windows {
+                self.select_window_events(*window);
+            }
+        }
+        self.adopt_existing_windows(screen.root()).log();
+        self.update_desktops_hints().log();
+        self.update_client_list().log();
+        self.update_work_area().log();
+        self.conn.flush();
+        self.change_to_current_workspace();
+    }
+
+    /// Sets up windows which were already mapped before umberwm started, or hidden on another
+    /// workspace (`Iconic`) by a previous instance, placing them on their `_NET_WM_DESKTOP`
+    /// workspace when it is set.
+    fn adopt_existing_windows(&mut self, root: Window) -> Result<()> {
+        let tree = xcb::query_tree(&self.conn, root).get_reply()?;
+        let workspaces_names = self.workspaces_names();
+        for window in tree.children() {
+            let attributes = match xcb::get_window_attributes(&self.conn, *window).get_reply() {
+                Ok(attributes) => attributes,
+                Err(_) => continue,
+            };
+            let viewable = attributes.map_state() == xcb::MAP_STATE_VIEWABLE as u8;
+            if attributes.override_redirect()
+                || (!viewable
+                    && get_wm_state(&self.conn, *window).log().flatten() != Some(WM_STATE_ICONIC))
+                || self.overlay_windows.contains(window)
+                || self.workspace_of_window(*window).is_some()
+            {
+                continue;
+            }
+            let target_workspace = get_cardinal_property(&self.conn, *window, "_NET_WM_DESKTOP")
+                .log()
+                .flatten()
+                .and_then(|desktop| workspaces_names.get(desktop as usize).cloned())
+                .unwrap_or_else(|| self.current_workspace.clone());
+            self.setup_new_window(*window, target_workspace).log();
+            if !viewable
+                && self
+                    .workspace_of_window(*window)
+                    .is_some_and(|workspace| self.is_workspace_visible(&workspace))
+            {
+                xcb::map_window(&self.conn, *window);
+            }
+        }
+        Ok(())
+    }
+
+    fn workspaces_names(&self) -> Vec<WorkspaceName> {
+        self.conf
+            .serializable
+            .workspaces_names
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the EWMH desktop number of the workspace.
+    fn desktop_of_workspace(&self, workspace: &str) -> Option<u32> {
+        self.workspaces_names()
+            .iter()
+            .position(|name| name == workspace)
+            .map(|i| i as u32)
+    }
+
+    /// Exports workspaces as EWMH desktops, along with the desktop of each window.
+    fn update_desktops_hints(&self) -> Result<()> {
+        let root = get_root(&self.conn)?;
+        let workspaces_names = self.workspaces_names();
+        set_cardinal_property(
+            &self.conn,
+            root,
+            "_NET_NUMBER_OF_DESKTOPS",
+            &[workspaces_names.len() as u32],
+        )?;
+        set_desktop_names(&self.conn, root, &workspaces_names)?;
+        for (name, workspace) in &self.workspaces {
+            for window in &workspace.windows {
+                self.set_window_desktop(*window, name)?;
+            }
+        }
+        self.set_current_desktop()
+    }
+
+    fn set_current_desktop(&self) -> Result<()> {
+        if let Some(desktop) = self.desktop_of_workspace(&self.current_workspace) {
+            let root = get_root(&self.conn)?;
+            set_cardinal_property(&self.conn, root, "_NET_CURRENT_DESKTOP", &[desktop])?;
+        }
+        Ok(())
+    }
+
+    /// Publishes managed windows in `_NET_CLIENT_LIST`, and in stacking order in
+    /// `_NET_CLIENT_LIST_STACKING`.
+    fn update_client_list(&self) -> Result<()> {
+        let root = get_root(&self.conn)?;
+        let clients: Vec<Window> = self
+            .workspaces_names()
+            .iter()
+            .filter_map(|name| self.workspaces.get(name))
+            .flat_map(|workspace| workspace.windows.clone())
+            .collect();
+        let stacking: Vec<Window> = xcb::query_tree(&self.conn, root)
+            .get_reply()?
+            .children()
+            .iter()
+            .filter(|window| clients.contains(window))
+            .copied()
+            .collect();
+        set_window_list_property(&self.conn, root, "_NET_CLIENT_LIST", &clients)?;
+        set_window_list_property(&self.conn, root, "_NET_CLIENT_LIST_STACKING", &stacking)
+    }
+
+    fn set_window_desktop(&self, window: Window, workspace: &str) -> Result<()> {
+        if let Some(desktop) = self.desktop_of_workspace(workspace) {
+            set_cardinal_property(&self.conn, window, "_NET_WM_DESKTOP", &[desktop])?;
+        }
+        Ok(())
+    }
+
+    /// Selects substructure redirection on the root window, exiting if another window manager
+    /// already owns it.
+    fn select_root_events(&self, screen: &xcb::Screen) {
+        let redirect = xcb::change_window_attributes_checked(
             &self.conn,
             screen.root(),
             &[(
                 xcb::CW_EVENT_MASK,
-                xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY as u32,
+                xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY | xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT,
             )],
-        );
-        self.conn.flush();
-        self.change_to_current_workspace();
+        )
+        .request_check();
+        if let Err(e) = redirect {
+            if e.error_code() == xcb::ACCESS {
+                eprintln!("{}", Error::AnotherWmRunning);
+                std::process::exit(1);
+            }
+            eprintln!("{}", e);
+        }
     }
 
     fn grab_custom_action_keys(&self, screen: &xcb::Screen) {
@@ -231,7 +826,7 @@ impl UmberWm {
     fn focus_unfocus(&mut self, window: &xcb::Window, do_focus: bool) -> Result<()> {
         let mut border_focus = false;
         if do_focus {
-            xcb::set_input_focus(&self.conn, xcb::INPUT_FOCUS_PARENT as u8, *window, 0);
+            self.give_input_focus(*window).log();
             let workspace = self
                 .workspaces
                 .get_mut(&self.current_workspace)
@@ -256,43 +851,353 @@ impl UmberWm {
                 32,
                 &data[..],
             );
+            if self.urgent_windows.contains(window) {
+                self.urgent_windows.retain(|x| x != window);
+                self.update_net_wm_state(*window)?;
+            }
+            for (transient, _) in self.transients.iter().filter(|(_, x)| *x == window) {
+                xcb::configure_window(
+                    &self.conn,
+                    *transient,
+                    &[(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)],
+                );
+            }
+        }
+        xcb::change_window_attributes(
+            &self.conn,
+            *window,
+            &[(
+                xcb::CW_BORDER_PIXEL,
+                if border_focus {
+                    self.conf.serializable.border.focus_color
+                } else if self.urgent_windows.contains(window) {
+                    self.conf.serializable.border.urgent_color
+                } else {
+                    self.conf.serializable.border.normal_color
+                },
+            )],
+        );
+        Ok(())
+    }
+
+    /// Focuses the window following the ICCCM input model: passive and locally active
+    /// clients get the input focus, locally and globally active clients get `WM_TAKE_FOCUS`.
+    fn give_input_focus(&self, window: Window) -> Result<()> {
+        let input = get_wm_hints(&self.conn, window)?
+            .and_then(|hints| hints.input)
+            .unwrap_or(true);
+        if input {
+            xcb::set_input_focus(
+                &self.conn,
+                xcb::INPUT_FOCUS_PARENT as u8,
+                window,
+                self.last_event_time,
+            );
+        }
+        send_wm_protocol(&self.conn, window, "WM_TAKE_FOCUS", self.last_event_time)?;
+        Ok(())
+    }
+
+    /// Asks the window to close, or kills its client if it does not support
+    /// `WM_DELETE_WINDOW`. Windows supporting `_NET_WM_PING` are pinged so that
+    /// `OnUnresponsive` is triggered if they hang.
+    fn close_window(&mut self, window: Window) -> Result<()> {
+        if send_wm_protocol(&self.conn, window, "WM_DELETE_WINDOW", self.last_event_time)? {
+            if send_wm_protocol(&self.conn, window, "_NET_WM_PING", self.last_event_time)? {
+                self.pending_pings.insert(window, Instant::now());
+            }
+        } else {
+            xcb::kill_client(&self.conn, window);
+        }
+        self.conn.flush();
+        Ok(())
+    }
+
+    fn kill_window(&mut self, window: Window) {
+        self.pending_pings.remove(&window);
+        xcb::kill_client(&self.conn, window);
+        self.conn.flush();
+    }
+
+    /// Triggers `OnUnresponsive` for windows which did not answer a ping in time.
+    fn check_pending_pings(&mut self) {
+        let unresponsive: Vec<Window> = self
+            .pending_pings
+            .iter()
+            .filter(|(_, sent)| sent.elapsed() >= PING_TIMEOUT)
+            .map(|(window, _)| *window)
+            .collect();
+        for window in unresponsive {
+            self.pending_pings.remove(&window);
+            self.run_command_callback(
+                Events::OnUnresponsive,
+                vec![("%window_id%".to_string(), window.to_string())],
+            );
+        }
+    }
+
+    /// Switches to the workspace of the window and focuses it.
+    fn activate_window(&mut self, window: Window) -> Result<()> {
+        let workspace_name = self
+            .workspace_of_window(window)
+            .ok_or(Error::WindowNotFound)?;
+        if workspace_name != self.current_workspace {
+            self.switch_to_workspace(workspace_name.clone(), false);
+        }
+        let workspace = self
+            .workspaces
+            .get_mut(&workspace_name)
+            .ok_or(Error::WorkspaceNotFound)?;
+        if let Some(i) = workspace.windows.iter().position(|x| *x == window) {
+            workspace.focus = i;
+        }
+        self.resize_current_workspace()
+    }
+
+    /// Moves the window to another workspace, without changing the current workspace.
+    fn move_window_to_workspace(&mut self, window: Window, target: WorkspaceName) -> Result<()> {
+        let source = self
+            .workspace_of_window(window)
+            .ok_or(Error::WindowNotFound)?;
+        if !self.workspaces.contains_key(&target) {
+            return Err(Error::WorkspaceNotFound);
+        }
+        if source == target {
+            return Ok(());
+        }
+        let workspace = self
+            .workspaces
+            .get_mut(&source)
+            .ok_or(Error::WorkspaceNotFound)?;
+        workspace.windows.retain(|x| *x != window);
+        if workspace.focus >= workspace.windows.len() {
+            workspace.focus = workspace.windows.len().saturating_sub(1);
+        }
+        let workspace = self
+            .workspaces
+            .get_mut(&target)
+            .ok_or(Error::WorkspaceNotFound)?;
+        workspace.windows.push(window);
+        workspace.focus = workspace.windows.len() - 1;
+        if self.is_workspace_visible(&target) {
+            set_wm_state(&self.conn, window, WM_STATE_NORMAL)?;
+            xcb::map_window(&self.conn, window);
+        } else if self.is_workspace_visible(&source) {
+            set_wm_state(&self.conn, window, WM_STATE_ICONIC)?;
+            xcb::unmap_window(&self.conn, window);
+        }
+        self.set_window_desktop(window, &target)?;
+        self.update_client_list()?;
+        for transient in self.transients_of(window) {
+            self.move_window_to_workspace(transient, target.clone())?;
+        }
+        for workspace in &[source, target] {
+            if *workspace != self.current_workspace && self.is_workspace_visible(workspace) {
+                self.resize_workspace_windows(workspace, self.workspace_display(workspace));
+            }
+        }
+        self.resize_current_workspace()
+    }
+
+    /// Returns the tiled windows of the visible workspaces, with their workspace and geometry.
+    fn visible_tiles(&self) -> Vec<(WorkspaceName, Window, Geometry)> {
+        let mut workspaces_names: Vec<WorkspaceName> =
+            self.visible_workspaces.values().cloned().collect();
+        if !workspaces_names.contains(&self.current_workspace) {
+            workspaces_names.push(self.current_workspace.clone());
+        }
+        let mut tiles = vec![];
+        if self.displays_geometries.is_empty() {
+            return tiles;
+        }
+        for name in workspaces_names {
+            let workspace = match self.workspaces.get(&name) {
+                Some(workspace) => workspace,
+                None => continue,
+            };
+            let non_float_windows: Vec<Window> = workspace
+                .windows
+                .iter()
+                .filter(|window| !self.float_windows.contains(window))
+                .copied()
+                .collect();
+            if non_float_windows.is_empty() {
+                continue;
+            }
+            let display = self.workspace_display(&name);
+            let geos = self.layout_geometries(workspace, &non_float_windows, display);
+            let windows = if workspace.layout == Layout::Monocle {
+                workspace
+                    .windows
+                    .get(workspace.focus)
+                    .copied()
+                    .into_iter()
+                    .collect()
+            } else {
+                non_float_windows
+            };
+            for (window, geometry) in windows.into_iter().zip(geos) {
+                if !self.fullscreen_windows.contains(&window) {
+                    tiles.push((name.clone(), window, geometry));
+                }
+            }
+        }
+        tiles
+    }
+
+    /// Focuses, or swaps the focused window with, the nearest tiled window in the direction,
+    /// looking at the adjacent display when there is none on this one.
+    fn focus_direction(&mut self, direction: Direction, swap: bool) -> Result<()> {
+        if self.displays_geometries.is_empty() {
+            return Ok(());
+        }
+        let focused = self
+            .workspaces
+            .get(&self.current_workspace)
+            .and_then(|workspace| workspace.windows.get(workspace.focus))
+            .copied();
+        let display = self.workspace_display(&self.current_workspace);
+        let tiles = self.visible_tiles();
+        let from = tiles
+            .iter()
+            .find(|(_, window, _)| Some(*window) == focused)
+            .map(|(_, _, geometry)| geometry.clone())
+            .unwrap_or_else(|| self.displays_geometries[display].clone());
+        let geometries: Vec<Geometry> = tiles.iter().map(|(_, _, x)| x.clone()).collect();
+        if let Some(i) = nearest_in_direction(&from, &geometries, &direction) {
+            let target = tiles[i].1;
+            return match (swap, focused) {
+                (true, Some(window)) => self.swap_windows(window, target),
+                (true, None) => Ok(()),
+                (false, _) => self.activate_window(target),
+            };
+        }
+        let display_geometry = self.displays_geometries[display].clone();
+        let target_display =
+            nearest_in_direction(&display_geometry, &self.displays_geometries, &direction);
+        if let Some(workspace) = target_display.and_then(|x| self.visible_workspace(x)) {
+            match (swap, focused) {
+                (true, Some(window)) => {
+                    self.move_window_to_workspace(window, workspace)?;
+                    self.activate_window(window)?;
+                }
+                (true, None) => {}
+                (false, _) => self.switch_to_workspace(workspace, false),
+            }
+        }
+        Ok(())
+    }
+
+    fn window_position(&self, workspace: &str, window: Window) -> Result<usize> {
+        self.workspaces
+            .get(workspace)
+            .and_then(|workspace| workspace.windows.iter().position(|x| *x == window))
+            .ok_or(Error::WindowNotFound)
+    }
+
+    /// Exchanges the places of two windows, which may be in different workspaces. The first
+    /// window keeps the focus.
+    fn swap_windows(&mut self, window: Window, other: Window) -> Result<()> {
+        let workspace_name = self
+            .workspace_of_window(window)
+            .ok_or(Error::WindowNotFound)?;
+        let other_workspace_name = self
+            .workspace_of_window(other)
+            .ok_or(Error::WindowNotFound)?;
+        let i = self.window_position(&workspace_name, window)?;
+        let j = self.window_position(&other_workspace_name, other)?;
+        for (name, index, replacement) in &[
+            (&workspace_name, i, other),
+            (&other_workspace_name, j, window),
+        ] {
+            let workspace = self
+                .workspaces
+                .get_mut(*name)
+                .ok_or(Error::WorkspaceNotFound)?;
+            workspace.windows[*index] = *replacement;
+        }
+        if workspace_name != other_workspace_name {
+            for (moved, name) in &[(window, &other_workspace_name), (other, &workspace_name)] {
+                self.set_window_desktop(*moved, name)?;
+                for transient in self.transients_of(*moved) {
+                    self.move_window_to_workspace(transient, (*name).clone())?;
+                }
+            }
+            self.update_client_list()?;
+            self.resize_workspace_windows(&workspace_name, self.workspace_display(&workspace_name));
+        }
+        self.activate_window(window)
+    }
+
+    fn transients_of(&self, window: Window) -> Vec<Window> {
+        self.transients
+            .iter()
+            .filter(|(_, parent)| **parent == window)
+            .map(|(transient, _)| *transient)
+            .collect()
+    }
+
+    fn handle_client_message(&mut self, event: &xcb::ClientMessageEvent) -> Result<()> {
+        let message_type = event.type_();
+        let window = event.window();
+        let data = event.data().data32();
+        let workspaces_names = self.workspaces_names();
+        if message_type == get_atom(&self.conn, "_NET_ACTIVE_WINDOW")? {
+            self.activate_window(window)?;
+        } else if message_type == get_atom(&self.conn, "_NET_CURRENT_DESKTOP")? {
+            let name = workspaces_names
+                .get(data[0] as usize)
+                .ok_or(Error::WorkspaceNotFound)?;
+            self.switch_to_workspace(name.clone(), false);
+        } else if message_type == get_atom(&self.conn, "WM_PROTOCOLS")?
+            && data[0] == get_atom(&self.conn, "_NET_WM_PING")?
+        {
+            self.pending_pings.remove(&data[2]);
+        } else if message_type == get_atom(&self.conn, "_NET_CLOSE_WINDOW")? {
+            self.close_window(window)?;
+        } else if message_type == get_atom(&self.conn, "_NET_WM_STATE")? {
+            self.handle_net_wm_state_message(window, data)?;
+        } else if message_type == get_atom(&self.conn, "_NET_WM_DESKTOP")? {
+            let name = workspaces_names
+                .get(data[0] as usize)
+                .ok_or(Error::WorkspaceNotFound)?;
+            self.move_window_to_workspace(window, name.clone())?;
         }
-        xcb::change_window_attributes(
-            &self.conn,
-            *window,
-            &[(
-                xcb::CW_BORDER_PIXEL,
-                if border_focus {
-                    self.conf.serializable.border.focus_color
-                } else {
-                    self.conf.serializable.border.normal_color
-                },
-            )],
-        );
         Ok(())
     }
 
-    fn serialize_and_quit(&mut self) -> Result<()> {
-        self.run_command_callback(Events::OnSerializeAndQuit, vec![]);
-        let mut file = File::create(UMBERWM_STATE)?;
-        let string = to_string(&SerializableState {
+    fn serializable_state(&self) -> SerializableState {
+        SerializableState {
             float_windows: self.float_windows.clone(),
             overlay_windows: self.overlay_windows.clone(),
+            fullscreen_windows: self.fullscreen_windows.clone(),
+            transients: self.transients.clone(),
+            float_geometries: self.float_geometries.clone(),
             workspaces: self.workspaces.clone(),
             current_workspace: self.current_workspace.clone(),
-        })?;
+        }
+    }
+
+    fn serialize_and_quit(&mut self) -> Result<()> {
+        self.run_command_callback(Events::OnSerializeAndQuit, vec![]);
+        let mut file = File::create(UMBERWM_STATE)?;
+        let string = to_string(&self.serializable_state())?;
         file.write_all(string.as_bytes())?;
         std::process::exit(123);
     }
 
     fn run_wm_action(&mut self, keybind: &Keybind) -> Result<()> {
-        let workspaces_names_by_display = self.conf.serializable.workspaces_names.clone();
         let action = self
             .conf
             .serializable
             .wm_actions
             .get(keybind)
-            .ok_or(Error::ActionNotFound)?;
+            .ok_or(Error::ActionNotFound)?
+            .clone();
+        self.run_action(&action)
+    }
+
+    fn run_action(&mut self, action: &Actions) -> Result<()> {
         let workspace = self
             .workspaces
             .get_mut(&self.current_workspace)
@@ -303,26 +1208,62 @@ impl UmberWm {
                     .windows
                     .get(workspace.focus)
                     .ok_or(Error::WindowNotFound)?);
-                let wm_delete_window = xcb::intern_atom(&self.conn, false, "WM_DELETE_WINDOW")
-                    .get_reply()?
-                    .atom();
-                let wm_protocols = xcb::intern_atom(&self.conn, false, "WM_PROTOCOLS")
-                    .get_reply()?
-                    .atom();
-                let data = xcb::ClientMessageData::from_data32([
-                    wm_delete_window,
-                    xcb::CURRENT_TIME,
-                    0,
-                    0,
-                    0,
-                ]);
-                let ev = xcb::ClientMessageEvent::new(32, window, wm_protocols, data);
-                xcb::send_event(&self.conn, false, window, xcb::EVENT_MASK_NO_EVENT, &ev);
-                self.conn.flush();
+                self.close_window(window)?;
+            }
+            Actions::ToggleFloat => {
+                let window = *(workspace
+                    .windows
+                    .get(workspace.focus)
+                    .ok_or(Error::WindowNotFound)?);
+                self.toggle_float(window)?;
+            }
+            Actions::FocusLeft => self.focus_direction(Direction::Left, false)?,
+            Actions::FocusRight => self.focus_direction(Direction::Right, false)?,
+            Actions::FocusUp => self.focus_direction(Direction::Up, false)?,
+            Actions::FocusDown => self.focus_direction(Direction::Down, false)?,
+            Actions::SwapLeft => self.focus_direction(Direction::Left, true)?,
+            Actions::SwapRight => self.focus_direction(Direction::Right, true)?,
+            Actions::SwapUp => self.focus_direction(Direction::Up, true)?,
+            Actions::SwapDown => self.focus_direction(Direction::Down, true)?,
+            Actions::SendToWorkspace(target) => {
+                let window = *(workspace
+                    .windows
+                    .get(workspace.focus)
+                    .ok_or(Error::WindowNotFound)?);
+                if *target != self.current_workspace {
+                    self.move_window_to_workspace(window, target.clone())?;
+                    self.run_command_callback(
+                        Events::OnSendToWorkspace,
+                        vec![
+                            ("%window_id%".to_string(), window.to_string()),
+                            ("%workspace_name%".to_string(), target.clone()),
+                        ],
+                    );
+                }
+            }
+            Actions::KillWindow => {
+                let window = *(workspace
+                    .windows
+                    .get(workspace.focus)
+                    .ok_or(Error::WindowNotFound)?);
+                self.kill_window(window);
             }
             Actions::SerializeAndQuit => {
                 self.serialize_and_quit().log();
             }
+            Actions::FocusUrgent => {
+                if let Some(window) = self.urgent_windows.first() {
+                    self.activate_window(*window)?;
+                }
+            }
+            Actions::ToggleFullscreen => {
+                let window = *(workspace
+                    .windows
+                    .get(workspace.focus)
+                    .ok_or(Error::WindowNotFound)?);
+                let fullscreen = !self.fullscreen_windows.contains(&window);
+                self.set_fullscreen(window, fullscreen)?;
+            }
             Actions::SwitchWindow => {
                 if !workspace.windows.is_empty() {
                     workspace.focus = (workspace.focus + 1) % workspace.windows.len();
@@ -350,20 +1291,42 @@ impl UmberWm {
                 }
             }
             Actions::DecreaseQuota => {
-                if workspace.quota > 0.1 {
+                if !resize_focused_split(workspace, -0.1) && workspace.quota > 0.1 {
                     workspace.quota -= 0.1
                 }
             }
             Actions::IncreaseQuota => {
-                if workspace.quota < 0.9 {
+                if !resize_focused_split(workspace, 0.1) && workspace.quota < 0.9 {
                     workspace.quota += 0.1
                 }
             }
-            Actions::ChangeLayout => {
-                workspace.layout = match workspace.layout {
-                    Layout::Bspv => Layout::Monocle,
-                    Layout::Monocle => Layout::Bsph,
-                    Layout::Bsph => Layout::Bspv,
+            Actions::IncreaseMasterCount => {
+                if workspace.master_count < workspace.windows.len() {
+                    workspace.master_count += 1
+                }
+            }
+            Actions::DecreaseMasterCount => {
+                if workspace.master_count > 0 {
+                    workspace.master_count -= 1
+                }
+            }
+            Actions::PromoteToMaster => {
+                if workspace.focus > 0 {
+                    let window = workspace.windows.remove(workspace.focus);
+                    workspace.windows.insert(0, window);
+                    workspace.focus = 0;
+                } else if workspace.windows.len() > 1 {
+                    // the master is already focused: swap it with the next window, as dwm does
+                    workspace.windows.swap(0, 1);
+                }
+            }
+            Actions::ChangeLayout | Actions::PreviousLayout | Actions::SetLayout(_) => {
+                workspace.layout = match action {
+                    Actions::SetLayout(layout) => layout.clone(),
+                    Actions::PreviousLayout => {
+                        cycle_layout(&self.conf.serializable.layouts, &workspace.layout, false)
+                    }
+                    _ => cycle_layout(&self.conf.serializable.layouts, &workspace.layout, true),
                 };
                 let layout_string = layout_to_string(&workspace.layout);
                 self.run_command_callback(
@@ -384,20 +1347,10 @@ impl UmberWm {
                 std::process::exit(0)
             }
         };
-        for (display, workspaces_names) in workspaces_names_by_display.iter().enumerate() {
-            if workspaces_names.contains(&self.current_workspace) {
-                let workspace = self
-                    .workspaces
-                    .get(&self.current_workspace)
-                    .ok_or(Error::WorkspaceNotFound)?
-                    .clone();
-                self.resize_workspace_windows(&workspace, display);
-            }
-        }
-        Ok(())
+        self.resize_current_workspace()
     }
 
-    fn setup_new_window(&mut self, window: u32) -> Result<()> {
+    fn setup_new_window(&mut self, window: u32, mut target_workspace: WorkspaceName) -> Result<()> {
         for workspace in self.workspaces.values() {
             for workspace_window in &workspace.windows {
                 if &window == workspace_window {
@@ -450,7 +1403,16 @@ impl UmberWm {
             self.overlay_windows.push(window);
             return Ok(());
         }
-        if window_types.contains(&window_type)
+        if window_type == get_atom(&self.conn, "_NET_WM_WINDOW_TYPE_DOCK")? {
+            return self.add_dock(window);
+        }
+        let transient_for = get_transient_for(&self.conn, window)?
+            .filter(|parent| self.workspace_of_window(*parent).is_some())
+            .filter(|_| {
+                !window_types.contains(&window_type)
+                    || get_atom(&self.conn, "_NET_WM_WINDOW_TYPE_DIALOG").ok() == Some(window_type)
+            });
+        if (transient_for.is_none() && window_types.contains(&window_type))
             || "_KDE_NET_WM_WINDOW_TYPE_OVERRIDE"
                 == xcb::get_atom_name(&self.conn, window_type)
                     .get_reply()?
@@ -458,7 +1420,6 @@ impl UmberWm {
         {
             return Ok(());
         }
-        let mut target_workspace = self.current_workspace.clone();
         if !wm_class.is_empty() {
             if is_firefox_drag_n_drop_initialization_window(&self.conn, window, &wm_class)? {
                 return Ok(());
@@ -480,44 +1441,139 @@ impl UmberWm {
                 }
             }
         }
+        if let Some(parent) = transient_for {
+            if let Some(parent_workspace) = self.workspace_of_window(parent) {
+                target_workspace = parent_workspace;
+            }
+            self.transients.insert(window, parent);
+            center_window_over(&self.conn, window, parent).log();
+        }
         if let Some(workspace) = self.workspaces.get_mut(&target_workspace) {
             if !workspace.windows.contains(&window) {
-                if !wm_class.is_empty()
-                    && self
-                        .conf
-                        .serializable
-                        .float_classes
-                        .contains(&wm_class[0].to_string())
+                if (transient_for.is_some()
+                    || !wm_class.is_empty()
+                        && self
+                            .conf
+                            .serializable
+                            .float_classes
+                            .contains(&wm_class[0].to_string()))
                     && !self.float_windows.contains(&window)
                 {
                     self.float_windows.push(window);
                 }
-                workspace.windows.push(window);
-                workspace.focus = workspace.windows.len() - 1;
-                let workspace2 = workspace.clone();
+                // new windows split the focused window
+                let index = if workspace.windows.is_empty() {
+                    0
+                } else {
+                    workspace.focus + 1
+                };
+                workspace.windows.insert(index, window);
+                workspace.focus = index;
+                if get_atoms_property(&self.conn, window, "_NET_WM_STATE")?
+                    .contains(&get_atom(&self.conn, "_NET_WM_STATE_FULLSCREEN")?)
+                    && !self.fullscreen_windows.contains(&window)
+                {
+                    self.fullscreen_windows.push(window);
+                }
+                self.set_window_desktop(window, &target_workspace).log();
+                self.update_client_list().log();
                 let workspaces_names_by_display = self.conf.serializable.workspaces_names.clone();
                 for (display, workspaces_names) in workspaces_names_by_display.iter().enumerate() {
                     if workspaces_names.contains(&self.current_workspace)
                         && self.current_workspace == target_workspace
                     {
-                        self.resize_workspace_windows(&workspace2, display);
+                        self.resize_workspace_windows(&target_workspace, display);
                     }
                 }
             }
         }
+        self.select_window_events(window);
         if self.current_workspace == target_workspace {
+            set_wm_state(&self.conn, window, WM_STATE_NORMAL)?;
+        } else {
+            set_wm_state(&self.conn, window, WM_STATE_ICONIC)?;
+            xcb::unmap_window(&self.conn, window);
+        }
+        Ok(())
+    }
+
+    fn select_window_events(&self, window: Window) {
+        xcb::change_window_attributes(
+            &self.conn,
+            window,
+            &[(
+                xcb::CW_EVENT_MASK,
+                xcb::EVENT_MASK_ENTER_WINDOW
+                    | xcb::EVENT_MASK_LEAVE_WINDOW
+                    | xcb::EVENT_MASK_PROPERTY_CHANGE,
+            )],
+        );
+    }
+
+    /// Marks the window as demanding attention, or clears it. The focused window is never
+    /// marked.
+    fn set_urgent(&mut self, window: Window, urgent: bool) -> Result<()> {
+        let workspace_name = self
+            .workspace_of_window(window)
+            .ok_or(Error::WindowNotFound)?;
+        let focused = workspace_name == self.current_workspace
+            && self
+                .workspaces
+                .get(&workspace_name)
+                .and_then(|workspace| workspace.windows.get(workspace.focus))
+                == Some(&window);
+        if urgent && !focused && !self.urgent_windows.contains(&window) {
+            self.urgent_windows.push(window);
             xcb::change_window_attributes(
                 &self.conn,
                 window,
                 &[(
-                    xcb::CW_EVENT_MASK,
-                    xcb::EVENT_MASK_ENTER_WINDOW | xcb::EVENT_MASK_LEAVE_WINDOW,
+                    xcb::CW_BORDER_PIXEL,
+                    self.conf.serializable.border.urgent_color,
                 )],
             );
+            self.run_command_callback(
+                Events::OnUrgent,
+                vec![
+                    ("%window_id%".to_string(), window.to_string()),
+                    ("%workspace_name%".to_string(), workspace_name),
+                ],
+            );
+        } else if !urgent && self.urgent_windows.contains(&window) {
+            self.urgent_windows.retain(|x| *x != window);
         } else {
-            xcb::unmap_window(&self.conn, window);
+            return Ok(());
+        }
+        self.update_net_wm_state(window)
+    }
+
+    fn workspace_of_window(&self, window: Window) -> Option<WorkspaceName> {
+        self.workspaces
+            .iter()
+            .find(|(_, workspace)| workspace.windows.contains(&window))
+            .map(|(name, _)| name.clone())
+    }
+
+    fn handle_map_request(&mut self, window: Window) {
+        self.setup_new_window(window, self.current_workspace.clone())
+            .log();
+        match self.workspace_of_window(window) {
+            Some(workspace) if workspace != self.current_workspace => {}
+            _ => {
+                xcb::map_window(&self.conn, window);
+            }
+        }
+    }
+
+    fn handle_configure_request(&mut self, event: &xcb::ConfigureRequestEvent) {
+        let window = event.window();
+        let tiled = self.workspace_of_window(window).is_some()
+            && (!self.float_windows.contains(&window) || self.fullscreen_windows.contains(&window));
+        if tiled {
+            send_configure_notify(&self.conn, window).log();
+        } else {
+            xcb::configure_window(&self.conn, window, &configure_request_values(event));
         }
-        Ok(())
     }
 
     fn resize_window(&mut self, event: &xcb::MotionNotifyEvent) -> Result<()> {
@@ -525,6 +1581,12 @@ impl UmberWm {
             .mouse_move_start
             .clone()
             .ok_or(Error::NoMouseMoveStart)?;
+        if let Some(tiled_resize_start) = self.tiled_resize_start.clone() {
+            return self.resize_tiled_window(event, &mouse_move_start, &tiled_resize_start);
+        }
+        if mouse_move_start.detail == 1 && self.is_tiled(mouse_move_start.child) {
+            self.update_drop_target(mouse_move_start.child, event.root_x(), event.root_y());
+        }
         let attr = self
             .button_press_geometry
             .clone()
@@ -574,17 +1636,225 @@ impl UmberWm {
         Ok(())
     }
 
+    /// Returns whether the window is tiled in the current workspace.
+    fn is_tiled(&self, window: Window) -> bool {
+        self.workspaces
+            .get(&self.current_workspace)
+            .is_some_and(|workspace| workspace.windows.contains(&window))
+            && !self.float_windows.contains(&window)
+            && !self.fullscreen_windows.contains(&window)
+    }
+
+    /// Returns the tiled window of the current workspace under the pointer, other than `window`.
+    fn tiled_window_at(&self, window: Window, x: i16, y: i16) -> Option<Window> {
+        let workspace = self.workspaces.get(&self.current_workspace)?;
+        workspace.windows.iter().copied().find(|other| {
+            *other != window
+                && self.is_tiled(*other)
+                && xcb::get_geometry(&self.conn, *other)
+                    .get_reply()
+                    .is_ok_and(|geometry| {
+                        x >= geometry.x()
+                            && y >= geometry.y()
+                            && (x as i32) < geometry.x() as i32 + geometry.width() as i32
+                            && (y as i32) < geometry.y() as i32 + geometry.height() as i32
+                    })
+        })
+    }
+
+    /// Highlights the tile the dragged window would be swapped with if dropped now.
+    fn update_drop_target(&mut self, window: Window, x: i16, y: i16) {
+        let drop_target = self.tiled_window_at(window, x, y);
+        if drop_target == self.drop_target {
+            return;
+        }
+        if let Some(previous) = self.drop_target {
+            self.focus_unfocus(&previous, false).log();
+        }
+        if let Some(target) = drop_target {
+            xcb::change_window_attributes(
+                &self.conn,
+                target,
+                &[(
+                    xcb::CW_BORDER_PIXEL,
+                    self.conf.serializable.border.drop_target_color,
+                )],
+            );
+        }
+        self.drop_target = drop_target;
+    }
+
+    /// Drops a tiled window moved or resized with the mouse: when moved, it is swapped with the
+    /// tile it was dropped on, or moved to the workspace shown on the display it was dropped on,
+    /// or else floats where it was left. When resized, it goes back to its tile.
+    fn drop_tiled_window(&mut self, window: Window, detail: u8, x: i16, y: i16) -> Result<()> {
+        let drop_target = self.drop_target.take();
+        let other_display = self
+            .display_at(x, y)
+            .filter(|display| *display != self.workspace_display(&self.current_workspace))
+            .and_then(|display| self.visible_workspace(display));
+        match (detail, drop_target, other_display) {
+            (1, Some(target), _) => {
+                let workspace = self
+                    .workspaces
+                    .get_mut(&self.current_workspace)
+                    .ok_or(Error::WorkspaceNotFound)?;
+                let i = workspace.windows.iter().position(|x| *x == window);
+                let j = workspace.windows.iter().position(|x| *x == target);
+                if let (Some(i), Some(j)) = (i, j) {
+                    workspace.windows.swap(i, j);
+                    workspace.focus = j;
+                }
+            }
+            (1, None, Some(workspace)) => return self.move_window_to_workspace(window, workspace),
+            (1, None, None) => {
+                self.float_windows.push(window);
+                self.save_float_geometry(window)?;
+            }
+            _ => {}
+        }
+        self.resize_current_workspace()
+    }
+
+    fn display_at(&self, x: i16, y: i16) -> Option<DisplayId> {
+        let (x, y) = (x as i32, y as i32);
+        self.displays_geometries
+            .iter()
+            .position(|Geometry(left, top, width, height)| {
+                x >= *left as i32
+                    && y >= *top as i32
+                    && x < (left + width) as i32
+                    && y < (top + height) as i32
+            })
+    }
+
+    /// Returns the workspace shown on the display: the last one switched to on this display,
+    /// or else its first workspace.
+    fn visible_workspace(&self, display: DisplayId) -> Option<WorkspaceName> {
+        self.visible_workspaces.get(&display).cloned().or_else(|| {
+            self.conf
+                .serializable
+                .workspaces_names
+                .get(display)
+                .and_then(|names| names.first().cloned())
+        })
+    }
+
+    fn is_workspace_visible(&self, workspace: &str) -> bool {
+        self.current_workspace == workspace
+            || self
+                .visible_workspaces
+                .values()
+                .any(|name| name == workspace)
+    }
+
+    /// Returns what dragging the window moves, if it is tiled in a layout with a resizable split:
+    /// the split of the window in the bsp layouts, or the workspace quota.
+    fn tiled_resize_start(&self, window: Window) -> Option<TiledResizeStart> {
+        if !self.is_tiled(window) || self.displays_geometries.is_empty() {
+            return None;
+        }
+        let workspace = self.workspaces.get(&self.current_workspace)?;
+        let area = self.tiling_area(self.workspace_display(&self.current_workspace));
+        let (ratio, length, along_x) = match (&workspace.layout, &workspace.tree) {
+            (Layout::Bspv, Some(tree)) | (Layout::Bsph, Some(tree)) => {
+                let vertical = if workspace.layout == Layout::Bspv {
+                    1
+                } else {
+                    0
+                };
+                let (split, splits_height) = split_area(tree, window, 0, area.clone(), vertical)?;
+                let ratio = tree.split_ratio(window)?;
+                if splits_height {
+                    (ratio, split.3, false)
+                } else {
+                    (ratio, split.2, true)
+                }
+            }
+            (Layout::Tall, _) | (Layout::CenteredMaster, _) | (Layout::Spiral, _) => {
+                (workspace.quota, area.2, true)
+            }
+            (Layout::Wide, _) => (workspace.quota, area.3, false),
+            _ => return None,
+        };
+        Some(TiledResizeStart {
+            window,
+            ratio,
+            length,
+            along_x,
+        })
+    }
+
+    /// Moves the split being dragged by the distance the pointer moved since the button press.
+    fn resize_tiled_window(
+        &mut self,
+        event: &xcb::MotionNotifyEvent,
+        mouse_move_start: &MouseMoveStart,
+        start: &TiledResizeStart,
+    ) -> Result<()> {
+        let diff = if start.along_x {
+            event.root_x() - mouse_move_start.root_x
+        } else {
+            event.root_y() - mouse_move_start.root_y
+        };
+        let ratio = (start.ratio + diff as f32 / max(start.length, 1) as f32).clamp(0.1, 0.9);
+        let workspace = self
+            .workspaces
+            .get_mut(&self.current_workspace)
+            .ok_or(Error::WorkspaceNotFound)?;
+        match (&workspace.layout, &mut workspace.tree) {
+            (Layout::Bspv, Some(tree)) | (Layout::Bsph, Some(tree)) => {
+                tree.set_split_ratio(start.window, ratio);
+            }
+            _ => workspace.quota = ratio,
+        }
+        self.resize_current_workspace()
+    }
+
+    /// Unmanages windows withdrawn by their client. Clients withdrawing a window which is
+    /// already unmapped (e.g. on a hidden workspace) send a synthetic UnmapNotify instead.
+    fn handle_unmap_notify(&mut self, event: &xcb::UnmapNotifyEvent) {
+        let window = event.window();
+        let synthetic = event.response_type() & 0x80 != 0;
+        if self.docks.remove(&window).is_some() {
+            self.update_work_area().log();
+        }
+        if self.workspace_of_window(window).is_some()
+            && (synthetic || self.is_withdrawn(window).unwrap_or(false))
+        {
+            set_wm_state(&self.conn, window, WM_STATE_WITHDRAWN).log();
+            self.destroy_window(window);
+        }
+    }
+
+    /// Windows umberwm hides are set to `Iconic` before being unmapped, so an unmapped window
+    /// still in the `Normal` state has been withdrawn by its client.
+    fn is_withdrawn(&self, window: Window) -> Result<bool> {
+        let attributes = xcb::get_window_attributes(&self.conn, window).get_reply()?;
+        Ok(attributes.map_state() == xcb::MAP_STATE_UNMAPPED as u8
+            && get_wm_state(&self.conn, window)? == Some(WM_STATE_NORMAL))
+    }
+
     fn destroy_window(&mut self, window: u32) {
+        if self.docks.remove(&window).is_some() {
+            self.update_work_area().log();
+        }
         self.overlay_windows.retain(|&x| x != window);
         self.float_windows.retain(|&x| x != window);
-        let mut workspace2: Option<Workspace> = None;
-        for workspace in self.workspaces.values_mut() {
+        self.fullscreen_windows.retain(|&x| x != window);
+        self.float_geometries.remove(&window);
+        self.urgent_windows.retain(|&x| x != window);
+        self.pending_pings.remove(&window);
+        self.transients
+            .retain(|transient, parent| *transient != window && *parent != window);
+        let mut workspace2: Option<(WorkspaceName, Workspace)> = None;
+        for (name, workspace) in self.workspaces.iter_mut() {
             if workspace.windows.contains(&window) {
                 workspace.windows.retain(|&x| x != window);
                 if workspace.focus > 0 {
                     workspace.focus -= 1;
                 }
-                workspace2 = Some(workspace.clone());
+                workspace2 = Some((name.clone(), workspace.clone()));
             }
         }
         let workspaces_names_by_display = self.conf.serializable.workspaces_names.clone();
@@ -595,58 +1865,278 @@ impl UmberWm {
             }
         }
 
-        if let Some(workspace) = workspace2 {
+        if let Some((name, workspace)) = workspace2 {
             workspace
                 .windows
                 .get(workspace.focus)
                 .map(|previous_window| self.focus_unfocus(previous_window, true));
-            self.resize_workspace_windows(&workspace, dis);
+            self.resize_workspace_windows(&name, dis);
+            self.update_client_list().log();
         }
     }
 
     pub fn run(&mut self) {
         loop {
-            if let Some(event) = self.conn.wait_for_event() {
-                let r = event.response_type();
-                if r == xcb::MAP_NOTIFY as u8 {
-                    let map_notify: &xcb::MapNotifyEvent = unsafe { xcb::cast_event(&event) };
-                    self.setup_new_window(map_notify.window()).log();
-                }
-                if r == self.randr_base + randr::NOTIFY {
-                    self.displays_geometries = get_displays_geometries(&self.conn).unwrap();
-                }
-                if r == xcb::DESTROY_NOTIFY as u8 {
-                    let map_notify: &xcb::DestroyNotifyEvent = unsafe { xcb::cast_event(&event) };
-                    let window_string = map_notify.window().to_string();
-                    self.run_command_callback(
-                        Events::OnCloseWindow,
-                        vec![("%window_id%".to_string(), window_string)],
-                    );
-                    self.destroy_window(map_notify.window());
-                } else if r == xcb::BUTTON_PRESS as u8 {
-                    let event: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(&event) };
-                    self.handle_button_press(event);
-                } else if r == xcb::MOTION_NOTIFY as u8 {
-                    let event: &xcb::MotionNotifyEvent = unsafe { xcb::cast_event(&event) };
-                    self.resize_window(event).log();
-                } else if r == xcb::LEAVE_NOTIFY as u8 {
-                    let event: &xcb::LeaveNotifyEvent = unsafe { xcb::cast_event(&event) };
-                    self.focus_unfocus(&event.event(), false).log();
-                } else if r == xcb::ENTER_NOTIFY as u8 {
-                    let event: &xcb::EnterNotifyEvent = unsafe { xcb::cast_event(&event) };
-                    self.focus_unfocus(&event.event(), true).log();
-                } else if r == xcb::BUTTON_RELEASE as u8 {
-                    self.mouse_move_start = None;
-                } else if r == xcb::KEY_PRESS as u8 {
-                    let event: &xcb::KeyPressEvent = unsafe { xcb::cast_event(&event) };
-                    self.handle_key_press(event);
-                }
+            self.handle_ipc_connections();
+            self.ipc_subscribers
+                .retain_mut(|subscriber| subscriber.flush().is_ok());
+            while let Some(event) = self.conn.poll_for_event() {
+                self.handle_event(&event);
             }
             self.conn.flush();
+            if let Err(e) = self.conn.has_error() {
+                eprintln!("X connection error: {:?}", e);
+                std::process::exit(1);
+            }
+            let mut fds = vec![self.conn.as_raw_fd()];
+            if let Some(listener) = &self.ipc_listener {
+                fds.push(listener.as_raw_fd());
+            }
+            fds.extend(
+                self.ipc_clients
+                    .iter()
+                    .map(|(stream, _)| stream.as_raw_fd()),
+            );
+            let writable_fds: Vec<_> = self
+                .ipc_subscribers
+                .iter()
+                .filter(|subscriber| subscriber.has_pending_output())
+                .map(|subscriber| subscriber.stream.as_raw_fd())
+                .collect();
+            // wake up when the first pending ping times out
+            let timeout = self
+                .pending_pings
+                .values()
+                .map(|sent| PING_TIMEOUT.saturating_sub(sent.elapsed()))
+                .min();
+            ipc::wait_for_io(&fds, &writable_fds, timeout);
+            self.check_pending_pings();
+        }
+    }
+
+    fn handle_event(&mut self, event: &xcb::GenericEvent) {
+        // events sent by other clients have their most significant bit set
+        let r = event.response_type() & !0x80;
+        if let Some(time) = event_time(event) {
+            self.last_event_time = time;
+        }
+        if r == xcb::MAP_REQUEST {
+            let map_request: &xcb::MapRequestEvent = unsafe { xcb::cast_event(event) };
+            self.handle_map_request(map_request.window());
+        } else if r == xcb::CONFIGURE_REQUEST {
+            let event: &xcb::ConfigureRequestEvent = unsafe { xcb::cast_event(event) };
+            self.handle_configure_request(event);
+        } else if r == xcb::CLIENT_MESSAGE {
+            let event: &xcb::ClientMessageEvent = unsafe { xcb::cast_event(event) };
+            self.handle_client_message(event).log();
+        } else if r == xcb::PROPERTY_NOTIFY {
+            let event: &xcb::PropertyNotifyEvent = unsafe { xcb::cast_event(event) };
+            self.handle_property_notify(event).log();
+        } else if r == xcb::UNMAP_NOTIFY {
+            let event: &xcb::UnmapNotifyEvent = unsafe { xcb::cast_event(event) };
+            self.handle_unmap_notify(event);
         }
+        if r == self.randr_base + randr::NOTIFY {
+            self.displays_geometries = get_displays_geometries(&self.conn).unwrap();
+            if let Some(screen_size) = get_screen_size(&self.conn).log() {
+                self.screen_size = screen_size;
+            }
+            self.update_work_area().log();
+        }
+        if r == xcb::DESTROY_NOTIFY as u8 {
+            let map_notify: &xcb::DestroyNotifyEvent = unsafe { xcb::cast_event(event) };
+            let window_string = map_notify.window().to_string();
+            self.run_command_callback(
+                Events::OnCloseWindow,
+                vec![("%window_id%".to_string(), window_string)],
+            );
+            self.destroy_window(map_notify.window());
+        } else if r == xcb::BUTTON_PRESS as u8 {
+            let event: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(event) };
+            self.handle_button_press(event);
+        } else if r == xcb::MOTION_NOTIFY as u8 {
+            let event: &xcb::MotionNotifyEvent = unsafe { xcb::cast_event(event) };
+            self.resize_window(event).log();
+        } else if r == xcb::LEAVE_NOTIFY as u8 {
+            let event: &xcb::LeaveNotifyEvent = unsafe { xcb::cast_event(event) };
+            self.focus_unfocus(&event.event(), false).log();
+        } else if r == xcb::ENTER_NOTIFY as u8 {
+            let event: &xcb::EnterNotifyEvent = unsafe { xcb::cast_event(event) };
+            self.focus_unfocus(&event.event(), true).log();
+        } else if r == xcb::BUTTON_RELEASE as u8 {
+            let event: &xcb::ButtonReleaseEvent = unsafe { xcb::cast_event(event) };
+            self.handle_button_release(event);
+        } else if r == xcb::KEY_PRESS as u8 {
+            let event: &xcb::KeyPressEvent = unsafe { xcb::cast_event(event) };
+            self.handle_key_press(event);
+        }
+    }
+
+    fn handle_ipc_connections(&mut self) {
+        if let Some(listener) = &self.ipc_listener {
+            while let Ok((stream, _)) = listener.accept() {
+                if stream.set_nonblocking(true).log().is_some() {
+                    self.ipc_clients.push((stream, vec![]));
+                }
+            }
+        }
+        let mut lines = vec![];
+        let mut clients = vec![];
+        for (stream, mut buffer) in self.ipc_clients.drain(..) {
+            match ipc::read_line(&stream, &mut buffer) {
+                Ok(Some(line)) => lines.push((stream, line)),
+                Ok(None) => clients.push((stream, buffer)),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        self.ipc_clients = clients;
+        for (stream, line) in lines {
+            self.handle_ipc_client(stream, &line).log();
+        }
+    }
+
+    fn handle_ipc_client(&mut self, stream: UnixStream, line: &str) -> Result<()> {
+        let command = line.parse::<IpcCommand>();
+        if let Ok(IpcCommand::Subscribe(format)) = command {
+            self.ipc_subscribers
+                .push(IpcSubscriber::new(stream, format));
+            return Ok(());
+        }
+        let reply = match command.and_then(|command| self.run_ipc_command(command)) {
+            Ok(reply) => reply,
+            Err(e) => format!("error: {:?}", e),
+        };
+        writeln!(&stream, "{}", reply)?;
+        Ok(())
+    }
+
+    fn run_ipc_command(&mut self, command: IpcCommand) -> Result<String> {
+        match command {
+            IpcCommand::Action(action) => self.run_action(&action)?,
+            IpcCommand::Workspace(name)
+            | IpcCommand::MoveToWorkspace(name)
+            | IpcCommand::SendToWorkspace(name)
+                if !self.workspaces.contains_key(&name) =>
+            {
+                return Err(Error::WorkspaceNotFound)
+            }
+            IpcCommand::Workspace(key) => self.switch_to_workspace(key, false),
+            IpcCommand::MoveToWorkspace(key) => self.switch_to_workspace(key, true),
+            IpcCommand::SendToWorkspace(key) => self.run_action(&Actions::SendToWorkspace(key))?,
+            IpcCommand::KillWindow(window) => {
+                if self.workspace_of_window(window).is_none() {
+                    return Err(Error::WindowNotFound);
+                }
+                self.kill_window(window)
+            }
+            // subscriptions keep the connection and are handled in `handle_ipc_client`
+            IpcCommand::Subscribe(_) => {
+                return Err(Error::InvalidIpcCommand("subscribe".to_string()))
+            }
+            IpcCommand::Query(query, format) => {
+                return match query {
+                    IpcQuery::Workspaces => format.serialize(&self.workspaces_snapshot()),
+                    IpcQuery::Tree => format.serialize(&self.tree_snapshot()),
+                    IpcQuery::Displays => format.serialize(&self.displays_snapshot()),
+                    IpcQuery::Focused => format.serialize(&self.focused_window_snapshot()),
+                }
+            }
+        }
+        Ok("ok".to_string())
+    }
+
+    fn window_snapshot(&self, window: Window) -> WindowSnapshot {
+        let wm_class = get_str_property(&self.conn, window, "WM_CLASS").unwrap_or_default();
+        let mut wm_class = wm_class.split('\0').map(|x| x.to_string());
+        let workspace = self.workspace_of_window(window);
+        let focused = workspace.as_ref() == Some(&self.current_workspace)
+            && self
+                .workspaces
+                .get(&self.current_workspace)
+                .and_then(|workspace| workspace.windows.get(workspace.focus))
+                == Some(&window);
+        WindowSnapshot {
+            id: window,
+            instance: wm_class.next().unwrap_or_default(),
+            class: wm_class.next().unwrap_or_default(),
+            title: get_window_title(&self.conn, window)
+                .log()
+                .unwrap_or_default(),
+            workspace,
+            floating: self.float_windows.contains(&window),
+            focused,
+        }
+    }
+
+    fn workspaces_snapshot(&self) -> Vec<WorkspaceSnapshot> {
+        let mut snapshots = vec![];
+        for (display, workspaces_names) in
+            self.conf.serializable.workspaces_names.iter().enumerate()
+        {
+            for name in workspaces_names {
+                if let Some(workspace) = self.workspaces.get(name) {
+                    snapshots.push(WorkspaceSnapshot {
+                        name: name.clone(),
+                        display,
+                        current: name == &self.current_workspace,
+                        workspace: workspace.clone(),
+                        windows: workspace
+                            .windows
+                            .iter()
+                            .map(|window| self.window_snapshot(*window))
+                            .collect(),
+                    });
+                }
+            }
+        }
+        snapshots
+    }
+
+    fn tree_snapshot(&self) -> TreeSnapshot {
+        let windows = self
+            .workspaces_snapshot()
+            .iter()
+            .flat_map(|snapshot| snapshot.workspace.windows.clone())
+            .chain(self.overlay_windows.clone())
+            .map(|window| self.window_snapshot(window))
+            .collect();
+        TreeSnapshot {
+            state: self.serializable_state(),
+            windows,
+        }
+    }
+
+    fn displays_snapshot(&self) -> Vec<DisplaySnapshot> {
+        self.displays_geometries
+            .iter()
+            .enumerate()
+            .map(|(id, geometry)| DisplaySnapshot {
+                id,
+                geometry: geometry.clone(),
+                border: self.display_border(id),
+                workspaces: self
+                    .conf
+                    .serializable
+                    .workspaces_names
+                    .get(id)
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    fn focused_window_snapshot(&self) -> Option<WindowSnapshot> {
+        let workspace = self.workspaces.get(&self.current_workspace)?;
+        let window = workspace.windows.get(workspace.focus)?;
+        Some(self.window_snapshot(*window))
     }
 
     fn handle_button_press(&mut self, event: &xcb::ButtonPressEvent) {
+        self.tiled_resize_start = if event.detail() == 3 {
+            self.tiled_resize_start(event.child())
+        } else {
+            None
+        };
         if let Ok(geometry) = xcb::get_geometry(&self.conn, event.child()).get_reply() {
             self.button_press_geometry = Some(Geometry(
                 geometry.x() as u32,
@@ -663,13 +2153,40 @@ impl UmberWm {
         });
     }
 
+    fn handle_button_release(&mut self, event: &xcb::ButtonReleaseEvent) {
+        let tiled_resize_start = self.tiled_resize_start.take();
+        if let Some(mouse_move_start) = self.mouse_move_start.take() {
+            let window = mouse_move_start.child;
+            let moved = event.root_x() != mouse_move_start.root_x
+                || event.root_y() != mouse_move_start.root_y;
+            if moved && tiled_resize_start.is_none() && self.is_tiled(window) {
+                self.drop_tiled_window(
+                    window,
+                    mouse_move_start.detail,
+                    event.root_x(),
+                    event.root_y(),
+                )
+                .log();
+            } else if self.float_windows.contains(&window) {
+                self.save_float_geometry(window).log();
+            }
+        }
+        if let Some(drop_target) = self.drop_target.take() {
+            self.focus_unfocus(&drop_target, false).log();
+        }
+    }
+
     fn handle_key_press(&mut self, event: &xcb::KeyPressEvent) {
         let keycode = event.detail();
         let mod_mask = event.state();
         if let Some(key) = &keycode::keycode_to_key(&self.xmodmap_pke, keycode) {
             let keybind = Keybind::new(mod_mask, key);
 
-            self.handle_workspace_change(&keybind);
+            // workspace keys are only grabbed with meta, and meta and shift
+            let meta = self.conf.serializable.meta;
+            if keybind.mod_mask == meta || keybind.mod_mask == meta | xcb::MOD_MASK_SHIFT {
+                self.handle_workspace_change(&keybind);
+            }
 
             if self.conf.serializable.wm_actions.contains_key(&keybind) {
                 self.run_wm_action(&keybind).log();
@@ -688,7 +2205,12 @@ impl UmberWm {
         }
     }
 
-    fn run_command_callback(&self, callback_name: Events, substitutions: Vec<(String, String)>) {
+    fn run_command_callback(
+        &mut self,
+        callback_name: Events,
+        substitutions: Vec<(String, String)>,
+    ) {
+        self.publish_event(&callback_name, &substitutions);
         if let Some(v) = self.conf.serializable.command_callbacks.get(&callback_name) {
             let res = v
                 .iter()
@@ -704,6 +2226,28 @@ impl UmberWm {
         }
     }
 
+    /// Sends the event to IPC subscribers, dropping those which went away or fell too far
+    /// behind.
+    fn publish_event(&mut self, event: &Events, substitutions: &[(String, String)]) {
+        if self.ipc_subscribers.is_empty() {
+            return;
+        }
+        let message = EventMessage {
+            event: event.clone(),
+            values: substitutions
+                .iter()
+                .map(|(name, value)| (name.trim_matches('%').to_string(), value.clone()))
+                .collect(),
+        };
+        self.ipc_subscribers.retain_mut(|subscriber| {
+            subscriber
+                .format
+                .serialize(&message)
+                .and_then(|line| subscriber.send_line(&line))
+                .is_ok()
+        });
+    }
+
     fn handle_workspace_change(&mut self, keybind: &Keybind) {
         let workspaces_names_by_display = self.conf.serializable.workspaces_names.clone();
         for (display, workspaces_names) in workspaces_names_by_display.iter().enumerate() {
@@ -727,12 +2271,29 @@ impl UmberWm {
                         .log()
                         .unwrap()
                         .clone();
-                    self.resize_workspace_windows(&workspace, display);
+                    if keybind.mod_mask & xcb::MOD_MASK_SHIFT != 0 {
+                        if let Some(window) = workspace.windows.get(workspace.focus) {
+                            self.set_window_desktop(*window, &self.current_workspace)
+                                .log();
+                            for transient in self.transients_of(*window) {
+                                self.move_window_to_workspace(
+                                    transient,
+                                    self.current_workspace.clone(),
+                                )
+                                .log();
+                            }
+                        }
+                    }
+                    self.set_current_desktop().log();
+                    let current_workspace = self.current_workspace.clone();
+                    self.resize_workspace_windows(&current_workspace, display);
                     let actual_display = if display >= self.displays_geometries.len() {
                         self.displays_geometries.len() - 1
                     } else {
                         display
                     };
+                    self.visible_workspaces
+                        .insert(actual_display, current_workspace);
                     if let Some(callback) = self.conf.events_callbacks.on_change_workspace.as_ref()
                     {
                         callback(keybind.key.clone(), actual_display)
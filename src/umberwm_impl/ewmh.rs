@@ -0,0 +1,130 @@
+use super::helpers::get_atom;
+use crate::error::Result;
+use crate::model::*;
+use xcb::xproto;
+
+/// EWMH hints umberwm supports, advertised in `_NET_SUPPORTED`.
+const SUPPORTED: &[&str] = &[
+    "_NET_SUPPORTED",
+    "_NET_SUPPORTING_WM_CHECK",
+    "_NET_WM_NAME",
+    "_NET_ACTIVE_WINDOW",
+    "_NET_NUMBER_OF_DESKTOPS",
+    "_NET_CURRENT_DESKTOP",
+    "_NET_CLOSE_WINDOW",
+    "_NET_DESKTOP_NAMES",
+    "_NET_WM_DESKTOP",
+    "_NET_CLIENT_LIST",
+    "_NET_CLIENT_LIST_STACKING",
+    "_NET_WM_WINDOW_TYPE",
+    "_NET_WM_STATE",
+    "_NET_WM_STATE_FULLSCREEN",
+    "_NET_WM_STATE_DEMANDS_ATTENTION",
+    "_NET_WORKAREA",
+    "_NET_WM_STRUT",
+    "_NET_WM_STRUT_PARTIAL",
+];
+
+pub fn set_cardinal_property(
+    conn: &xcb::Connection,
+    window: Window,
+    name: &str,
+    values: &[u32],
+) -> Result<()> {
+    xproto::change_property(
+        conn,
+        xcb::PROP_MODE_REPLACE as u8,
+        window,
+        get_atom(conn, name)?,
+        xproto::ATOM_CARDINAL,
+        32,
+        values,
+    );
+    Ok(())
+}
+
+pub fn set_window_list_property(
+    conn: &xcb::Connection,
+    window: Window,
+    name: &str,
+    windows: &[Window],
+) -> Result<()> {
+    xproto::change_property(
+        conn,
+        xcb::PROP_MODE_REPLACE as u8,
+        window,
+        get_atom(conn, name)?,
+        xproto::ATOM_WINDOW,
+        32,
+        windows,
+    );
+    Ok(())
+}
+
+fn set_utf8_property(
+    conn: &xcb::Connection,
+    window: Window,
+    name: &str,
+    data: &[u8],
+) -> Result<()> {
+    xproto::change_property(
+        conn,
+        xcb::PROP_MODE_REPLACE as u8,
+        window,
+        get_atom(conn, name)?,
+        get_atom(conn, "UTF8_STRING")?,
+        8,
+        data,
+    );
+    Ok(())
+}
+
+/// Sets `_NET_DESKTOP_NAMES` as a list of null-terminated UTF-8 strings.
+pub fn set_desktop_names(
+    conn: &xcb::Connection,
+    root: Window,
+    names: &[WorkspaceName],
+) -> Result<()> {
+    let data: Vec<u8> = names
+        .iter()
+        .flat_map(|name| name.bytes().chain(std::iter::once(0)))
+        .collect();
+    set_utf8_property(conn, root, "_NET_DESKTOP_NAMES", &data)
+}
+
+/// Creates the `_NET_SUPPORTING_WM_CHECK` window and advertises the supported hints, returning
+/// the check window.
+pub fn setup_supporting_wm_check(conn: &xcb::Connection, root: Window) -> Result<Window> {
+    let window = conn.generate_id();
+    xcb::create_window(
+        conn,
+        0,
+        window,
+        root,
+        -1,
+        -1,
+        1,
+        1,
+        0,
+        xcb::WINDOW_CLASS_INPUT_ONLY as u16,
+        0,
+        &[],
+    );
+    set_window_list_property(conn, window, "_NET_SUPPORTING_WM_CHECK", &[window])?;
+    set_utf8_property(conn, window, "_NET_WM_NAME", b"umberwm")?;
+    set_window_list_property(conn, root, "_NET_SUPPORTING_WM_CHECK", &[window])?;
+    let supported = SUPPORTED
+        .iter()
+        .map(|name| get_atom(conn, name))
+        .collect::<Result<Vec<xcb::Atom>>>()?;
+    xproto::change_property(
+        conn,
+        xcb::PROP_MODE_REPLACE as u8,
+        root,
+        get_atom(conn, "_NET_SUPPORTED")?,
+        xproto::ATOM_ATOM,
+        32,
+        &supported[..],
+    );
+    Ok(window)
+}
@@ -6,6 +6,13 @@ use std::thread;
 use xcb::randr;
 use xcb::xproto;
 
+const WM_HINTS_INPUT: u32 = 1;
+const WM_HINTS_URGENCY: u32 = 1 << 8;
+
+pub const WM_STATE_WITHDRAWN: u32 = 0;
+pub const WM_STATE_NORMAL: u32 = 1;
+pub const WM_STATE_ICONIC: u32 = 3;
+
 pub fn run_command(list: Option<&Vec<String>>) {
     if let Some(args) = list {
         if let Some(head) = args.first() {
@@ -18,6 +25,21 @@ pub fn run_command(list: Option<&Vec<String>>) {
     }
 }
 
+pub fn get_atom(conn: &xcb::Connection, name: &str) -> Result<xcb::Atom> {
+    Ok(xcb::intern_atom(conn, false, name).get_reply()?.atom())
+}
+
+pub fn get_root(conn: &xcb::Connection) -> Result<Window> {
+    let setup = conn.get_setup();
+    Ok(setup.roots().next().ok_or(Error::NoScreensFound)?.root())
+}
+
+/// Returns the size of the root window, which follows RandR changes unlike the connection setup.
+pub fn get_screen_size(conn: &xcb::Connection) -> Result<(u32, u32)> {
+    let geometry = xcb::get_geometry(conn, get_root(conn)?).get_reply()?;
+    Ok((geometry.width() as u32, geometry.height() as u32))
+}
+
 pub fn get_displays_geometries(conn: &xcb::Connection) -> Result<Vec<Geometry>> {
     let setup = conn.get_setup();
     let screen = setup.roots().next().unwrap();
@@ -90,6 +112,19 @@ pub fn get_str_property(conn: &xcb::Connection, window: u32, name: &str) -> Opti
     }
 }
 
+/// Returns the window title, from `_NET_WM_NAME` or else from `WM_NAME`.
+pub fn get_window_title(conn: &xcb::Connection, window: Window) -> Result<String> {
+    for name in &["_NET_WM_NAME", "WM_NAME"] {
+        let atom = get_atom(conn, name)?;
+        let reply = xproto::get_property(conn, false, window, atom, xproto::ATOM_ANY, 0, 1024)
+            .get_reply()?;
+        if reply.value_len() > 0 {
+            return Ok(String::from_utf8_lossy(reply.value()).to_string());
+        }
+    }
+    Ok(String::new())
+}
+
 pub fn get_wm_normal_hints(conn: &xcb::Connection, id: u32) -> Result<Option<NormalHints>> {
     let window: xproto::Window = id;
     let ident = xcb::intern_atom(conn, true, "WM_NORMAL_HINTS")
@@ -115,6 +150,95 @@ pub fn get_wm_normal_hints(conn: &xcb::Connection, id: u32) -> Result<Option<Nor
     }
 }
 
+pub fn get_wm_hints(conn: &xcb::Connection, window: Window) -> Result<Option<WmHints>> {
+    let reply = xproto::get_property(
+        conn,
+        false,
+        window,
+        xproto::ATOM_WM_HINTS,
+        xproto::ATOM_WM_HINTS,
+        0,
+        9,
+    )
+    .get_reply()?;
+    let value: &[u32] = reply.value();
+    if value.len() < 2 {
+        return Ok(None);
+    }
+    let flags = value[0];
+    Ok(Some(WmHints {
+        input: if flags & WM_HINTS_INPUT != 0 {
+            Some(value[1] != 0)
+        } else {
+            None
+        },
+        urgent: flags & WM_HINTS_URGENCY != 0,
+    }))
+}
+
+/// Sets the ICCCM `WM_STATE` of the window, without an icon window.
+pub fn set_wm_state(conn: &xcb::Connection, window: Window, state: u32) -> Result<()> {
+    let wm_state = get_atom(conn, "WM_STATE")?;
+    xproto::change_property(
+        conn,
+        xcb::PROP_MODE_REPLACE as u8,
+        window,
+        wm_state,
+        wm_state,
+        32,
+        &[state, xcb::NONE],
+    );
+    Ok(())
+}
+
+pub fn get_wm_state(conn: &xcb::Connection, window: Window) -> Result<Option<u32>> {
+    let wm_state = get_atom(conn, "WM_STATE")?;
+    let reply = xproto::get_property(conn, false, window, wm_state, wm_state, 0, 2).get_reply()?;
+    Ok(reply.value::<u32>().first().copied())
+}
+
+/// Returns the server timestamp of the event, for the events which carry one.
+pub fn event_time(event: &xcb::GenericEvent) -> Option<xcb::Timestamp> {
+    let r = event.response_type() & !0x80;
+    unsafe {
+        match r {
+            xcb::KEY_PRESS | xcb::KEY_RELEASE => {
+                Some(xcb::cast_event::<xcb::KeyPressEvent>(event).time())
+            }
+            xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
+                Some(xcb::cast_event::<xcb::ButtonPressEvent>(event).time())
+            }
+            xcb::MOTION_NOTIFY => Some(xcb::cast_event::<xcb::MotionNotifyEvent>(event).time()),
+            xcb::ENTER_NOTIFY | xcb::LEAVE_NOTIFY => {
+                Some(xcb::cast_event::<xcb::EnterNotifyEvent>(event).time())
+            }
+            xcb::PROPERTY_NOTIFY => Some(xcb::cast_event::<xcb::PropertyNotifyEvent>(event).time()),
+            _ => None,
+        }
+    }
+}
+
+/// Sends a `WM_PROTOCOLS` client message, if the window advertises the protocol.
+/// `time` has to be the timestamp of the event which triggered it, not `CurrentTime`.
+/// Returns whether the message was sent.
+pub fn send_wm_protocol(
+    conn: &xcb::Connection,
+    window: Window,
+    protocol: &str,
+    time: xcb::Timestamp,
+) -> Result<bool> {
+    let protocol = get_atom(conn, protocol)?;
+    if !get_atoms_property(conn, window, "WM_PROTOCOLS")?.contains(&protocol) {
+        return Ok(false);
+    }
+    let wm_protocols = get_atom(conn, "WM_PROTOCOLS")?;
+    // `_NET_WM_PING` replies echo the window, the other protocols ignore it
+    let data = xcb::ClientMessageData::from_data32([protocol, time, window, 0, 0]);
+    let ev = xcb::ClientMessageEvent::new(32, window, wm_protocols, data);
+    xcb::send_event(conn, false, window, xcb::EVENT_MASK_NO_EVENT, &ev);
+    Ok(true)
+}
+
 pub fn get_atom_property(conn: &xcb::Connection, id: u32, name: &str) -> Result<u32> {
     let window: xproto::Window = id;
     let ident = xcb::intern_atom(conn, true, name).get_reply()?.atom();
@@ -127,6 +251,98 @@ pub fn get_atom_property(conn: &xcb::Connection, id: u32, name: &str) -> Result<
     }
 }
 
+pub fn get_atoms_property(conn: &xcb::Connection, id: u32, name: &str) -> Result<Vec<xcb::Atom>> {
+    let window: xproto::Window = id;
+    let ident = get_atom(conn, name)?;
+    let reply =
+        xproto::get_property(conn, false, window, ident, xproto::ATOM_ATOM, 0, 1024).get_reply()?;
+    Ok(reply.value::<xcb::Atom>().to_vec())
+}
+
+pub fn get_cardinal_property(conn: &xcb::Connection, id: u32, name: &str) -> Result<Option<u32>> {
+    let window: xproto::Window = id;
+    let ident = get_atom(conn, name)?;
+    let reply = xproto::get_property(conn, false, window, ident, xproto::ATOM_CARDINAL, 0, 1)
+        .get_reply()?;
+    Ok(reply.value::<u32>().first().copied())
+}
+
+/// Reads `_NET_WM_STRUT_PARTIAL`, or else `_NET_WM_STRUT` which reserves whole edges.
+pub fn get_strut(conn: &xcb::Connection, window: Window) -> Result<Option<Strut>> {
+    for name in &["_NET_WM_STRUT_PARTIAL", "_NET_WM_STRUT"] {
+        let atom = get_atom(conn, name)?;
+        let reply = xproto::get_property(conn, false, window, atom, xproto::ATOM_CARDINAL, 0, 12)
+            .get_reply()?;
+        let value: &[u32] = reply.value();
+        if value.len() >= 12 {
+            return Ok(Some(Strut {
+                left: value[0],
+                right: value[1],
+                top: value[2],
+                bottom: value[3],
+                left_start_y: value[4],
+                left_end_y: value[5],
+                right_start_y: value[6],
+                right_end_y: value[7],
+                top_start_x: value[8],
+                top_end_x: value[9],
+                bottom_start_x: value[10],
+                bottom_end_x: value[11],
+            }));
+        } else if value.len() >= 4 {
+            return Ok(Some(Strut {
+                left: value[0],
+                right: value[1],
+                top: value[2],
+                bottom: value[3],
+                left_end_y: u32::MAX,
+                right_end_y: u32::MAX,
+                top_end_x: u32::MAX,
+                bottom_end_x: u32::MAX,
+                ..Strut::default()
+            }));
+        }
+    }
+    Ok(None)
+}
+
+pub fn get_transient_for(conn: &xcb::Connection, window: Window) -> Result<Option<Window>> {
+    let reply = xproto::get_property(
+        conn,
+        false,
+        window,
+        xproto::ATOM_WM_TRANSIENT_FOR,
+        xproto::ATOM_WINDOW,
+        0,
+        1,
+    )
+    .get_reply()?;
+    Ok(reply
+        .value::<Window>()
+        .first()
+        .copied()
+        .filter(|parent| *parent != xcb::NONE))
+}
+
+/// Moves the window so that it is centered over the parent window.
+pub fn center_window_over(conn: &xcb::Connection, window: Window, parent: Window) -> Result<()> {
+    let parent_geometry = xcb::get_geometry(conn, parent).get_reply()?;
+    let geometry = xcb::get_geometry(conn, window).get_reply()?;
+    let x =
+        parent_geometry.x() as i32 + (parent_geometry.width() as i32 - geometry.width() as i32) / 2;
+    let y = parent_geometry.y() as i32
+        + (parent_geometry.height() as i32 - geometry.height() as i32) / 2;
+    xcb::configure_window(
+        conn,
+        window,
+        &[
+            (xcb::CONFIG_WINDOW_X as u16, x as u32),
+            (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+        ],
+    );
+    Ok(())
+}
+
 pub fn is_firefox_drag_n_drop_initialization_window(
     conn: &xcb::Connection,
     id: u32,
@@ -166,6 +382,7 @@ fn unmap_workspace_windows(
         if move_window && i == focus {
             window_to_move = Some(*window);
         } else if same_display {
+            set_wm_state(conn, *window, WM_STATE_ICONIC).log();
             xcb::unmap_window(conn, *window);
         }
     }
@@ -202,6 +419,7 @@ pub fn change_workspace(
         .get_mut(&next_workspace)
         .ok_or(Error::WorkspaceNotFound)?;
     for window in &workspace.windows {
+        set_wm_state(conn, *window, WM_STATE_NORMAL).log();
         xcb::map_window(conn, *window);
     }
     if let Some(w) = window_to_move {
@@ -217,3 +435,48 @@ pub fn get_display_border(display_borders: &[DisplayBorder], display: usize) ->
     let i = std::cmp::min(display_borders.len() - 1, display);
     display_borders[i].clone()
 }
+
+/// Sends a synthetic ConfigureNotify with the current geometry of the window, telling the client
+/// its configure request was handled without moving it.
+pub fn send_configure_notify(conn: &xcb::Connection, window: Window) -> Result<()> {
+    let geometry = xcb::get_geometry(conn, window).get_reply()?;
+    let event = xcb::ConfigureNotifyEvent::new(
+        window,
+        window,
+        xcb::NONE,
+        geometry.x(),
+        geometry.y(),
+        geometry.width(),
+        geometry.height(),
+        geometry.border_width(),
+        false,
+    );
+    xcb::send_event(
+        conn,
+        false,
+        window,
+        xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+        &event,
+    );
+    Ok(())
+}
+
+/// Builds the configure values list requested by a ConfigureRequest event.
+pub fn configure_request_values(event: &xcb::ConfigureRequestEvent) -> Vec<(u16, u32)> {
+    let mask = event.value_mask();
+    let mut values = Vec::new();
+    for (flag, value) in &[
+        (xcb::CONFIG_WINDOW_X, event.x() as i32 as u32),
+        (xcb::CONFIG_WINDOW_Y, event.y() as i32 as u32),
+        (xcb::CONFIG_WINDOW_WIDTH, event.width() as u32),
+        (xcb::CONFIG_WINDOW_HEIGHT, event.height() as u32),
+        (xcb::CONFIG_WINDOW_BORDER_WIDTH, event.border_width() as u32),
+        (xcb::CONFIG_WINDOW_SIBLING, event.sibling()),
+        (xcb::CONFIG_WINDOW_STACK_MODE, event.stack_mode() as u32),
+    ] {
+        if mask & *flag as u16 != 0 {
+            values.push((*flag as u16, *value));
+        }
+    }
+    values
+}
@@ -31,24 +31,24 @@ pub fn resize_bsp(
 pub fn resize_monocle(
     conn: &xcb::Connection,
     border_width: u32,
-    workspace: &Workspace,
+    window: Option<&Window>,
     geos: Vec<Geometry>,
     gap: u32,
 ) {
-    if let Some(window) = workspace.windows.get(workspace.focus) {
+    if let (Some(window), Some(geo)) = (window, geos.first()) {
         xcb::configure_window(
             conn,
             *window,
             &[
-                (xcb::CONFIG_WINDOW_X as u16, geos[0].0 + gap),
-                (xcb::CONFIG_WINDOW_Y as u16, geos[0].1 + gap),
+                (xcb::CONFIG_WINDOW_X as u16, geo.0 + gap),
+                (xcb::CONFIG_WINDOW_Y as u16, geo.1 + gap),
                 (
                     xcb::CONFIG_WINDOW_WIDTH as u16,
-                    geos[0].2.saturating_sub(2 * border_width + 2 * gap),
+                    geo.2.saturating_sub(2 * border_width + 2 * gap),
                 ),
                 (
                     xcb::CONFIG_WINDOW_HEIGHT as u16,
-                    geos[0].3.saturating_sub(2 * border_width + 2 * gap),
+                    geo.3.saturating_sub(2 * border_width + 2 * gap),
                 ),
                 (xcb::CONFIG_WINDOW_BORDER_WIDTH as u16, border_width),
                 (xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE),